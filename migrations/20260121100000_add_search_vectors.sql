@@ -0,0 +1,31 @@
+-- Full-text search over stories (title, subtitle and the text inside the JSONB content)
+
+-- Extract the plain text of a story's content. Rich-text editors (TipTap/ProseMirror)
+-- store text in "text" keys at any depth; plain Markdown/HTML is stored as a JSON string.
+CREATE OR REPLACE FUNCTION story_content_text(content JSONB)
+RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN jsonb_typeof(content) = 'string' THEN content #>> '{}'
+        ELSE (
+            SELECT string_agg(t #>> '{}', ' ')
+            FROM jsonb_path_query(content, 'strict $.**.text') AS t
+            WHERE jsonb_typeof(t) = 'string'
+        )
+    END
+$$ LANGUAGE sql IMMUTABLE;
+
+ALTER TABLE stories ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', COALESCE(title, '')), 'A') ||
+    setweight(to_tsvector('english', COALESCE(subtitle, '')), 'B') ||
+    setweight(to_tsvector('english', COALESCE(story_content_text(content), '')), 'C')
+) STORED;
+
+CREATE INDEX idx_stories_search_vector ON stories USING GIN(search_vector);
+
+-- Users are matched by username (substring) and bio (full-text)
+ALTER TABLE users ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', username), 'A') ||
+    setweight(to_tsvector('english', COALESCE(bio, '')), 'B')
+) STORED;
+
+CREATE INDEX idx_users_search_vector ON users USING GIN(search_vector);
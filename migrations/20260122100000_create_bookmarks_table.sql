@@ -0,0 +1,11 @@
+-- Bookmarks table for users' reading lists
+CREATE TABLE bookmarks (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    story_id UUID NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, story_id)
+);
+
+-- Indexes for efficient queries
+CREATE INDEX idx_bookmarks_story ON bookmarks(story_id);
+CREATE INDEX idx_bookmarks_user_created_at ON bookmarks(user_id, created_at DESC);
@@ -0,0 +1,24 @@
+CREATE TYPE notification_type AS ENUM ('follow', 'story_clap', 'comment_clap', 'comment', 'reply');
+
+-- In-app notifications
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE, -- Recipient
+    actor_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE, -- Most recent user who triggered it
+    notification_type notification_type NOT NULL,
+    story_id UUID REFERENCES stories(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    event_count INTEGER NOT NULL DEFAULT 1, -- Number of grouped events (e.g. claps)
+    read_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Indexes for efficient queries
+CREATE INDEX idx_notifications_user_updated_at ON notifications(user_id, updated_at DESC, id DESC);
+CREATE INDEX idx_notifications_user_unread ON notifications(user_id) WHERE read_at IS NULL;
+
+-- Claps on the same story/comment are grouped into a single unread notification
+CREATE UNIQUE INDEX idx_notifications_unread_clap_group
+ON notifications(user_id, notification_type, (COALESCE(comment_id, story_id)))
+WHERE read_at IS NULL AND notification_type IN ('story_clap', 'comment_clap');
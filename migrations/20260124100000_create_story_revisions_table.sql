@@ -0,0 +1,26 @@
+-- Story revisions: a full snapshot of a story's editable fields after every write
+CREATE TABLE story_revisions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    story_id UUID NOT NULL REFERENCES stories(id) ON DELETE CASCADE,
+    revision_number INTEGER NOT NULL,
+    title VARCHAR(255) NOT NULL,
+    subtitle VARCHAR(255),
+    content JSONB NOT NULL,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Revision number this one was restored from, if it is a restore
+    restored_from INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (story_id, revision_number)
+);
+
+-- Existing stories start their history at revision 1
+INSERT INTO story_revisions (story_id, revision_number, title, subtitle, content, tags, created_by, created_at)
+SELECT
+    s.id, 1, s.title, s.subtitle, s.content,
+    COALESCE(
+        (SELECT ARRAY_AGG(t.name ORDER BY t.name) FROM story_tags st JOIN tags t ON st.tag_id = t.id WHERE st.story_id = s.id),
+        '{}'
+    ),
+    s.author_id, s.updated_at
+FROM stories s;
@@ -0,0 +1,7 @@
+-- Stories can be scheduled to publish at a future time
+ALTER TYPE story_status ADD VALUE 'scheduled';
+
+ALTER TABLE stories ADD COLUMN scheduled_for TIMESTAMPTZ;
+
+-- The background publisher scans for due scheduled stories
+CREATE INDEX idx_stories_scheduled_for ON stories(scheduled_for) WHERE scheduled_for IS NOT NULL;
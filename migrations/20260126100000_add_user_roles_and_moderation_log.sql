@@ -0,0 +1,21 @@
+-- Roles for access control; moderators and admins can act on other users' content
+CREATE TYPE user_role AS ENUM ('user', 'moderator', 'admin');
+
+ALTER TABLE users ADD COLUMN role user_role NOT NULL DEFAULT 'user';
+
+-- Audit log of moderator actions on content they don't own
+CREATE TYPE moderation_action AS ENUM ('delete_story', 'delete_comment', 'change_role');
+
+CREATE TABLE moderation_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    moderator_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    action moderation_action NOT NULL,
+    -- The target row may be gone (deleted content), so no foreign key
+    target_id UUID NOT NULL,
+    target_user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_moderation_log_created_at ON moderation_log(created_at DESC, id DESC);
+CREATE INDEX idx_moderation_log_target_user ON moderation_log(target_user_id);
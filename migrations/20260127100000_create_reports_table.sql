@@ -0,0 +1,34 @@
+-- User reports of abusive stories, comments and users, worked through by moderators
+CREATE TYPE report_target AS ENUM ('story', 'comment', 'user');
+CREATE TYPE report_reason AS ENUM ('spam', 'harassment', 'hate_speech', 'violence', 'sexual_content', 'misinformation', 'other');
+CREATE TYPE report_status AS ENUM ('open', 'actioned', 'dismissed');
+
+CREATE TABLE reports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    reporter_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_type report_target NOT NULL,
+    -- The target may be deleted later, so no foreign key
+    target_id UUID NOT NULL,
+    reason report_reason NOT NULL,
+    details TEXT,
+    status report_status NOT NULL DEFAULT 'open',
+    resolved_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    resolution_note TEXT,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- A user can only have one open report per target
+CREATE UNIQUE INDEX idx_reports_open_unique ON reports(reporter_id, target_type, target_id) WHERE status = 'open';
+CREATE INDEX idx_reports_status_created_at ON reports(status, created_at, id);
+CREATE INDEX idx_reports_target ON reports(target_type, target_id);
+
+-- Moderators can hide content without deleting it
+ALTER TABLE stories ADD COLUMN hidden_at TIMESTAMPTZ;
+ALTER TABLE comments ADD COLUMN hidden_at TIMESTAMPTZ;
+
+ALTER TYPE moderation_action ADD VALUE 'hide_story';
+ALTER TYPE moderation_action ADD VALUE 'unhide_story';
+ALTER TYPE moderation_action ADD VALUE 'hide_comment';
+ALTER TYPE moderation_action ADD VALUE 'unhide_comment';
+ALTER TYPE moderation_action ADD VALUE 'resolve_report';
@@ -0,0 +1,30 @@
+-- Lockout state: consecutive failed sign-ins, how many times the account was
+-- locked (each lockout lasts longer), and when the current lockout ends
+ALTER TABLE users
+    ADD COLUMN failed_login_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN lockout_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until TIMESTAMPTZ;
+
+CREATE TYPE login_failure_reason AS ENUM (
+    'invalid_password',
+    'unknown_account',
+    'account_locked',
+    'email_not_verified'
+);
+
+-- Every sign-in attempt, successful or not, for auditing and per-IP throttling
+CREATE TABLE login_attempts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- NULL when the email doesn't belong to any account
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    ip_address VARCHAR(45),
+    user_agent TEXT,
+    success BOOLEAN NOT NULL,
+    failure_reason login_failure_reason,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (success = (failure_reason IS NULL))
+);
+
+CREATE INDEX idx_login_attempts_user ON login_attempts(user_id, created_at DESC, id DESC);
+CREATE INDEX idx_login_attempts_ip ON login_attempts(ip_address, created_at DESC) WHERE NOT success;
@@ -0,0 +1,21 @@
+-- TOTP two-factor authentication. The secret is stored while enrollment is
+-- pending and 2FA only takes effect once totp_enabled_at is set.
+ALTER TABLE users
+    ADD COLUMN totp_secret VARCHAR(64),
+    ADD COLUMN totp_enabled_at TIMESTAMPTZ,
+    -- Last accepted time step, so a code can't be replayed
+    ADD COLUMN totp_last_step BIGINT;
+
+-- One-time recovery codes, hashed like passwords
+CREATE TABLE recovery_codes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_recovery_codes_user ON recovery_codes(user_id) WHERE used_at IS NULL;
+
+-- Wrong codes at the second sign-in step count towards the account lockout
+ALTER TYPE login_failure_reason ADD VALUE 'invalid_two_factor_code';
@@ -0,0 +1,3 @@
+-- Email change requests: the verification link is sent to the new address, which
+-- is kept on the token until it is confirmed ('email_change' token type)
+ALTER TABLE auth_tokens ADD COLUMN new_email VARCHAR(255);
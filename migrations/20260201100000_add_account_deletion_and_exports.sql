@@ -0,0 +1,23 @@
+-- Account deletion requests wait out a grace period before the user row (and,
+-- through ON DELETE CASCADE, everything they own) is removed
+ALTER TABLE users ADD COLUMN deletion_scheduled_for TIMESTAMPTZ;
+
+CREATE INDEX idx_users_deletion_scheduled ON users(deletion_scheduled_for)
+    WHERE deletion_scheduled_for IS NOT NULL;
+
+-- Personal data exports, built by a background worker
+CREATE TYPE export_status AS ENUM ('pending', 'processing', 'ready', 'failed');
+
+CREATE TABLE data_exports (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status export_status NOT NULL DEFAULT 'pending',
+    archive JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    -- Ready archives are removed after this
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_data_exports_user ON data_exports(user_id, created_at DESC);
+CREATE INDEX idx_data_exports_pending ON data_exports(created_at) WHERE status = 'pending';
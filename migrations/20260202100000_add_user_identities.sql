@@ -0,0 +1,30 @@
+-- Accounts at external OAuth2 / OpenID Connect providers linked to local users.
+-- The provider's subject identifier is what identifies the account, not the email.
+CREATE TABLE user_identities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    -- Email the provider reported when the identity was last used
+    email VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ,
+    UNIQUE (provider, subject),
+    -- One identity per provider and user
+    UNIQUE (user_id, provider)
+);
+
+-- Authorization requests waiting for the provider to redirect back. Each keeps the
+-- PKCE code verifier and the nonce expected in the ID token, and is used only once.
+CREATE TABLE oauth_states (
+    state VARCHAR(255) PRIMARY KEY,
+    provider VARCHAR(50) NOT NULL,
+    code_verifier VARCHAR(255) NOT NULL,
+    nonce VARCHAR(255) NOT NULL,
+    -- Set when a signed-in user is linking a provider instead of signing in
+    link_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_states_expires_at ON oauth_states(expires_at);
@@ -0,0 +1,7 @@
+-- Comments with replies are tombstoned instead of deleted, so the ON DELETE CASCADE
+-- on parent_id doesn't take the rest of the thread with them
+CREATE TYPE comment_deletion AS ENUM ('author', 'moderator');
+
+ALTER TABLE comments ADD COLUMN deleted_at TIMESTAMPTZ;
+-- Who removed the comment; moderator removals keep the original content for review
+ALTER TABLE comments ADD COLUMN deleted_by comment_deletion;
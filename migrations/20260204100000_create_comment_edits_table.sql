@@ -0,0 +1,13 @@
+-- Comment edit history: the content a comment had before each edit
+ALTER TABLE comments ADD COLUMN edited_at TIMESTAMPTZ;
+
+CREATE TABLE comment_edits (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    comment_id UUID NOT NULL REFERENCES comments(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    -- When this content was written: the comment's creation or the edit before
+    written_at TIMESTAMPTZ NOT NULL,
+    replaced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_comment_edits_comment ON comment_edits(comment_id, written_at DESC);
@@ -0,0 +1,10 @@
+-- Who may respond to a story
+CREATE TYPE comment_policy AS ENUM ('everyone', 'followers', 'disabled');
+
+ALTER TABLE stories
+    ADD COLUMN comment_policy comment_policy NOT NULL DEFAULT 'everyone',
+    ADD COLUMN pinned_comment_id UUID REFERENCES comments(id) ON DELETE SET NULL;
+
+-- Comments hidden by the story's author rather than by a moderator.
+-- Both set hidden_at; authors may only unhide the ones they hid themselves.
+ALTER TABLE comments ADD COLUMN hidden_by_author BOOLEAN NOT NULL DEFAULT FALSE;
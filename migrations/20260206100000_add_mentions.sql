@@ -0,0 +1,58 @@
+-- @username mentions in stories and comments, one row per mentioned user and target
+CREATE TABLE mentions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    story_id UUID REFERENCES stories(id) ON DELETE CASCADE,
+    comment_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+    -- Where the user is mentioned: [{"path": [...], "start": n, "end": n}, ...]
+    spans JSONB NOT NULL,
+    -- Set once the user has been told; stories notify when they are published
+    notified_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((story_id IS NULL) <> (comment_id IS NULL))
+);
+
+CREATE UNIQUE INDEX idx_mentions_story_user ON mentions(story_id, user_id) WHERE story_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_mentions_comment_user ON mentions(comment_id, user_id) WHERE comment_id IS NOT NULL;
+CREATE INDEX idx_mentions_user ON mentions(user_id);
+
+-- A target's mentions as spans for API responses, with each user's current username
+CREATE OR REPLACE FUNCTION story_mentions(target UUID)
+RETURNS JSONB AS $$
+    SELECT COALESCE(
+        jsonb_agg(
+            span || jsonb_build_object('user_id', u.id, 'username', u.username)
+            ORDER BY span->'path', (span->>'start')::int
+        ),
+        '[]'::jsonb
+    )
+    FROM mentions m
+    JOIN users u ON m.user_id = u.id
+    CROSS JOIN jsonb_array_elements(m.spans) AS span
+    WHERE m.story_id = target
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION comment_mentions(target UUID)
+RETURNS JSONB AS $$
+    SELECT COALESCE(
+        jsonb_agg(
+            span || jsonb_build_object('user_id', u.id, 'username', u.username)
+            ORDER BY (span->>'start')::int
+        ),
+        '[]'::jsonb
+    )
+    FROM mentions m
+    JOIN users u ON m.user_id = u.id
+    CROSS JOIN jsonb_array_elements(m.spans) AS span
+    WHERE m.comment_id = target
+$$ LANGUAGE sql STABLE;
+
+ALTER TYPE notification_type ADD VALUE 'mention';
+
+-- Per-user notification settings; users without a row get the defaults
+CREATE TABLE notification_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    mention_in_app BOOLEAN NOT NULL DEFAULT TRUE,
+    mention_email BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,7 @@
+-- Escape text for HTML, so search highlights can be built from user-written text
+-- with only the highlight tags left as markup
+CREATE OR REPLACE FUNCTION escape_html(text TEXT)
+RETURNS TEXT AS $$
+    SELECT replace(replace(replace(replace(replace(text,
+        '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), '"', '&quot;'), '''', '&#39;')
+$$ LANGUAGE sql IMMUTABLE;
@@ -0,0 +1,7 @@
+-- Deleting an account tombstones its comments that have replies instead of taking
+-- the rest of the thread with them, so comments can outlive their author
+ALTER TABLE comments ALTER COLUMN author_id DROP NOT NULL;
+
+ALTER TABLE comments DROP CONSTRAINT comments_author_id_fkey;
+ALTER TABLE comments ADD CONSTRAINT comments_author_id_fkey
+    FOREIGN KEY (author_id) REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,16 @@
+-- Usernames are limited to what @mentions can match: ASCII letters, digits and
+-- underscores, with dots and hyphens allowed inside the name but not at either end.
+-- Any existing name outside that gets its other characters replaced and a suffix
+-- from the user's id, which keeps it unique.
+UPDATE users SET username =
+    LEFT(
+        REGEXP_REPLACE(
+            REGEXP_REPLACE(username, '[^A-Za-z0-9_.-]', '_', 'g'),
+            '^[.-]+|[.-]+$', '', 'g'
+        ),
+        40
+    ) || '_' || LEFT(REPLACE(id::text, '-', ''), 8)
+WHERE username !~ '^[A-Za-z0-9_]([A-Za-z0-9_.-]*[A-Za-z0-9_])?$';
+
+ALTER TABLE users ADD CONSTRAINT users_username_format
+    CHECK (username ~ '^[A-Za-z0-9_]([A-Za-z0-9_.-]*[A-Za-z0-9_])?$');
@@ -0,0 +1,9 @@
+-- Exports left in 'processing' by a worker that died are picked up again after a
+-- timeout, and given up on after a few attempts
+ALTER TABLE data_exports ADD COLUMN started_at TIMESTAMPTZ;
+ALTER TABLE data_exports ADD COLUMN attempts INT NOT NULL DEFAULT 0;
+
+-- Anything already stuck counts as started now
+UPDATE data_exports SET started_at = NOW(), attempts = 1 WHERE status = 'processing';
+
+CREATE INDEX idx_data_exports_processing ON data_exports(started_at) WHERE status = 'processing';
@@ -0,0 +1,3 @@
+-- Feeds are ordered by publication time, falling back to creation for unpublished stories
+CREATE INDEX idx_stories_feed_order ON stories ((COALESCE(published_at, created_at)) DESC, id DESC)
+    WHERE status = 'published';
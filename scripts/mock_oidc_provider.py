@@ -0,0 +1,215 @@
+#!/usr/bin/env python3
+"""Minimal OpenID Connect provider for testing social sign-in locally.
+
+Implements discovery, an authorization endpoint that signs the user in straight
+away (no login page), a token endpoint that checks the PKCE code verifier, and
+the JWKS with the RS256 signing key. The claims of the signed-in user come from
+extra query parameters on the authorization URL:
+
+    sub, email, email_verified (true/false), preferred_username
+
+Pass tamper=1 as well to get an ID token signed with a key that is not published.
+
+Usage: python3 scripts/mock_oidc_provider.py [--port 9000] [--client-id blogverse]
+                                             [--client-secret secret]
+Requires the `cryptography` package.
+"""
+import argparse
+import base64
+import hashlib
+import json
+import secrets
+import time
+from http.server import BaseHTTPRequestHandler, ThreadingHTTPServer
+from urllib.parse import parse_qs, urlencode, urlparse
+
+from cryptography.hazmat.primitives import hashes
+from cryptography.hazmat.primitives.asymmetric import padding, rsa
+
+KEY_ID = "mock-key"
+
+
+def b64url(data: bytes) -> str:
+    return base64.urlsafe_b64encode(data).rstrip(b"=").decode()
+
+
+def int_to_b64url(value: int) -> str:
+    return b64url(value.to_bytes((value.bit_length() + 7) // 8, "big"))
+
+
+def sign_jwt(claims: dict, key) -> str:
+    header = {"alg": "RS256", "typ": "JWT", "kid": KEY_ID}
+    signing_input = (
+        b64url(json.dumps(header).encode()) + "." + b64url(json.dumps(claims).encode())
+    )
+    signature = key.sign(signing_input.encode(), padding.PKCS1v15(), hashes.SHA256())
+    return signing_input + "." + b64url(signature)
+
+
+class Provider:
+    def __init__(self, issuer, client_id, client_secret):
+        self.issuer = issuer
+        self.client_id = client_id
+        self.client_secret = client_secret
+        self.key = rsa.generate_private_key(public_exponent=65537, key_size=2048)
+        # Signs tampered tokens; never published
+        self.rogue_key = rsa.generate_private_key(public_exponent=65537, key_size=2048)
+        # Issued authorization codes, each usable once
+        self.codes = {}
+
+    def jwks(self):
+        numbers = self.key.public_key().public_numbers()
+        return {
+            "keys": [
+                {
+                    "kty": "RSA",
+                    "use": "sig",
+                    "alg": "RS256",
+                    "kid": KEY_ID,
+                    "n": int_to_b64url(numbers.n),
+                    "e": int_to_b64url(numbers.e),
+                }
+            ]
+        }
+
+
+def make_handler(provider: Provider):
+    class Handler(BaseHTTPRequestHandler):
+        def log_message(self, fmt, *args):
+            pass
+
+        def send_json(self, status, body):
+            data = json.dumps(body).encode()
+            self.send_response(status)
+            self.send_header("Content-Type", "application/json")
+            self.send_header("Content-Length", str(len(data)))
+            self.end_headers()
+            self.wfile.write(data)
+
+        def do_GET(self):
+            url = urlparse(self.path)
+            query = {k: v[0] for k, v in parse_qs(url.query).items()}
+
+            if url.path == "/.well-known/openid-configuration":
+                self.send_json(
+                    200,
+                    {
+                        "issuer": provider.issuer,
+                        "authorization_endpoint": provider.issuer + "/authorize",
+                        "token_endpoint": provider.issuer + "/token",
+                        "jwks_uri": provider.issuer + "/jwks",
+                        "response_types_supported": ["code"],
+                        "subject_types_supported": ["public"],
+                        "id_token_signing_alg_values_supported": ["RS256"],
+                        "code_challenge_methods_supported": ["S256"],
+                    },
+                )
+            elif url.path == "/jwks":
+                self.send_json(200, provider.jwks())
+            elif url.path == "/authorize":
+                self.authorize(query)
+            else:
+                self.send_json(404, {"error": "not_found"})
+
+        def do_POST(self):
+            if urlparse(self.path).path != "/token":
+                self.send_json(404, {"error": "not_found"})
+                return
+            length = int(self.headers.get("Content-Length", 0))
+            form = {k: v[0] for k, v in parse_qs(self.rfile.read(length).decode()).items()}
+            self.token(form)
+
+        def authorize(self, query):
+            required = ["response_type", "client_id", "redirect_uri", "state", "nonce",
+                        "code_challenge", "code_challenge_method"]
+            missing = [name for name in required if name not in query]
+            if missing:
+                self.send_json(400, {"error": "invalid_request", "missing": missing})
+                return
+            if query["client_id"] != provider.client_id:
+                self.send_json(400, {"error": "unauthorized_client"})
+                return
+            if query["response_type"] != "code" or query["code_challenge_method"] != "S256":
+                self.send_json(400, {"error": "unsupported_response_type"})
+                return
+
+            code = secrets.token_urlsafe(24)
+            provider.codes[code] = {
+                "redirect_uri": query["redirect_uri"],
+                "nonce": query["nonce"],
+                "code_challenge": query["code_challenge"],
+                "tamper": query.get("tamper") == "1",
+                "claims": {
+                    "sub": query.get("sub", "mock-user"),
+                    "email": query.get("email", "mock-user@example.com"),
+                    "email_verified": query.get("email_verified", "true") == "true",
+                    "preferred_username": query.get("preferred_username", "mockuser"),
+                },
+            }
+
+            location = query["redirect_uri"] + "?" + urlencode(
+                {"code": code, "state": query["state"]}
+            )
+            self.send_response(302)
+            self.send_header("Location", location)
+            self.send_header("Content-Length", "0")
+            self.end_headers()
+
+        def token(self, form):
+            if (form.get("client_id") != provider.client_id
+                    or form.get("client_secret") != provider.client_secret):
+                self.send_json(401, {"error": "invalid_client"})
+                return
+
+            grant = provider.codes.pop(form.get("code", ""), None)
+            if form.get("grant_type") != "authorization_code" or grant is None:
+                self.send_json(400, {"error": "invalid_grant"})
+                return
+            if form.get("redirect_uri") != grant["redirect_uri"]:
+                self.send_json(400, {"error": "invalid_grant", "detail": "redirect_uri"})
+                return
+
+            verifier = form.get("code_verifier", "")
+            if b64url(hashlib.sha256(verifier.encode()).digest()) != grant["code_challenge"]:
+                self.send_json(400, {"error": "invalid_grant", "detail": "code_verifier"})
+                return
+
+            now = int(time.time())
+            claims = dict(
+                grant["claims"],
+                iss=provider.issuer,
+                aud=provider.client_id,
+                iat=now,
+                exp=now + 300,
+                nonce=grant["nonce"],
+            )
+            key = provider.rogue_key if grant["tamper"] else provider.key
+
+            self.send_json(
+                200,
+                {
+                    "access_token": secrets.token_urlsafe(24),
+                    "token_type": "Bearer",
+                    "expires_in": 300,
+                    "id_token": sign_jwt(claims, key),
+                },
+            )
+
+    return Handler
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--port", type=int, default=9000)
+    parser.add_argument("--client-id", default="blogverse")
+    parser.add_argument("--client-secret", default="secret")
+    args = parser.parse_args()
+
+    provider = Provider(f"http://localhost:{args.port}", args.client_id, args.client_secret)
+    server = ThreadingHTTPServer(("127.0.0.1", args.port), make_handler(provider))
+    print(f"Mock OIDC provider listening on {provider.issuer}", flush=True)
+    server.serve_forever()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,167 @@
+use sqlx::{PgConnection, PgExecutor, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Spawn the background task that removes accounts whose deletion grace period is over.
+///
+/// Accounts are claimed with `FOR UPDATE SKIP LOCKED`, so several API instances
+/// can run this at once.
+pub fn spawn(pool: PgPool, interval_secs: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            match purge_due_accounts(&pool).await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!("Deleted {} accounts", purged),
+                Err(e) => tracing::error!("Account purge error: {:?}", e),
+            }
+        }
+    });
+}
+
+/// Delete every account past its scheduled deletion, one transaction each
+pub async fn purge_due_accounts(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut purged = 0;
+
+    loop {
+        let mut tx = pool.begin().await?;
+
+        let due: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM users
+            WHERE deletion_scheduled_for <= NOW()
+            ORDER BY deletion_scheduled_for
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(user_id) = due else {
+            return Ok(purged);
+        };
+
+        purge_user(&mut tx, user_id).await?;
+        tx.commit().await?;
+        purged += 1;
+    }
+}
+
+/// Remove a user and, through `ON DELETE CASCADE`, everything that belongs to them.
+/// Clap counts are denormalized onto stories and comments, so the user's claps on
+/// other people's content are taken off first. Comments are handled here rather than
+/// by cascade, so that replies from other people keep their thread.
+async fn purge_user(conn: &mut PgConnection, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE stories s SET clap_count = s.clap_count - sc.claps_count
+        FROM story_claps sc
+        WHERE sc.story_id = s.id AND sc.user_id = $1 AND s.author_id != $1
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE comments c SET clap_count = c.clap_count - cc.claps_count
+        FROM comment_claps cc
+        WHERE cc.comment_id = c.id AND cc.user_id = $1 AND c.author_id IS DISTINCT FROM $1
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+
+    purge_comments(&mut *conn, user_id).await?;
+
+    sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Remove a user's comments. Those with replies from anyone else, however deep, are
+/// wiped to "[deleted]" tombstones like an author's own deletion, and lose their
+/// author when the account goes. The rest are deleted along with the user's own
+/// replies to them, and tombstones this leaves without replies go too.
+async fn purge_comments(conn: &mut PgConnection, user_id: Uuid) -> Result<(), sqlx::Error> {
+    let kept: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE subtrees AS (
+            -- Each of the user's comments with everything below it
+            SELECT id AS root_id, id, author_id FROM comments WHERE author_id = $1
+            UNION
+            SELECT s.root_id, c.id, c.author_id FROM comments c
+            JOIN subtrees s ON c.parent_id = s.id
+        )
+        UPDATE comments SET
+            deleted_at = COALESCE(deleted_at, NOW()),
+            deleted_by = COALESCE(deleted_by, 'author'),
+            content = '[deleted]'
+        WHERE id IN (
+            SELECT root_id FROM subtrees WHERE author_id IS DISTINCT FROM $1
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // Nothing the user wrote stays behind in a tombstone
+    sqlx::query("DELETE FROM comment_edits WHERE comment_id = ANY($1)")
+        .bind(&kept)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM mentions WHERE comment_id = ANY($1)")
+        .bind(&kept)
+        .execute(&mut *conn)
+        .await?;
+
+    let parents: Vec<Option<Uuid>> = sqlx::query_scalar(
+        "DELETE FROM comments WHERE author_id = $1 AND id <> ALL($2) RETURNING parent_id",
+    )
+    .bind(user_id)
+    .bind(&kept)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for mut parent_id in parents {
+        while let Some(id) = parent_id {
+            parent_id = sqlx::query_scalar::<_, Option<Uuid>>(
+                r#"
+                DELETE FROM comments
+                WHERE id = $1 AND deleted_at IS NOT NULL
+                    AND NOT EXISTS(SELECT 1 FROM comments WHERE parent_id = $1)
+                RETURNING parent_id
+                "#,
+            )
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .flatten();
+        }
+    }
+
+    Ok(())
+}
+
+/// Call off a pending deletion; returns whether one was scheduled
+pub async fn cancel<'e>(executor: impl PgExecutor<'e>, user_id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE users SET deletion_scheduled_for = NULL WHERE id = $1 AND deletion_scheduled_for IS NOT NULL",
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -1,20 +1,37 @@
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{
+    extract::{ConnectInfo, Path, Query, State},
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
 use chrono::{Duration, Utc};
 use sqlx::PgPool;
+use std::net::SocketAddr;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     auth::{
-        jwt, utils, AuthResponse, AuthToken, ForgotPasswordRequest, LoginUser, RegisterUser,
-        ResendVerificationRequest, ResetPasswordRequest, User, UserResponse, VerifyEmailRequest,
+        deletion, jwt, lockout,
+        oauth::{self, OAuthClient},
+        session, totp, utils, AccountDeletionResponse, AuthResponse, AuthToken, ChangeEmailRequest,
+        ChangePasswordRequest, ConfirmEmailChangeRequest, DeleteAccountRequest,
+        ForgotPasswordRequest, IdentityResponse, LoginAttemptResponse, LoginFailure, LoginUser,
+        MagicLinkRequest, MagicLinkVerifyRequest, OAuthAuthorizeResponse, OAuthCallbackRequest,
+        RecoveryCodesResponse, RefreshTokenRequest, RegisterUser, ResendVerificationRequest,
+        ResetPasswordRequest, Role, SecurityLogFilter, Session, SessionResponse, TokenResponse,
+        TwoFactorChallengeResponse, TwoFactorCodeRequest, TwoFactorEnrollResponse,
+        TwoFactorVerifyRequest, UpdateProfile, User, UserResponse, VerifyEmailRequest,
     },
-    config::settings::Settings,
+    config::settings::{OAuthProviderConfig, Settings},
     email::EmailService,
     error::AppError,
-    response::ApiResponse,
+    response::{ApiResponse, Cursor, Pagination},
 };
 
+/// How long a magic sign-in link stays valid
+const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+
 /// POST /api/auth/sign-up
 /// Creates a new user and sends verification email
 pub async fn signup(
@@ -195,16 +212,43 @@ pub async fn resend_verification(
 }
 
 /// POST /api/auth/sign-in
-/// Login with email and password
+/// Login with email and password; repeated failures lock the account for a while
 pub async fn login(
     State(pool): State<PgPool>,
     State(settings): State<Settings>,
+    State(email_service): State<EmailService>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginUser>,
 ) -> Result<impl IntoResponse, AppError> {
     payload
         .validate()
         .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
 
+    let ip_address = addr.ip().to_string();
+
+    // Too many failures from this IP, whichever accounts they targeted
+    if let Some(retry_after) = lockout::ip_retry_after(&pool, &ip_address, &settings)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {:?}", e);
+            AppError::InternalServerError
+        })?
+    {
+        return Err(AppError::TooManyRequests(retry_after));
+    }
+
+    let record = |user_id: Option<Uuid>, failure: Option<LoginFailure>| {
+        lockout::record_attempt(
+            &pool,
+            user_id,
+            &payload.email,
+            &ip_address,
+            &headers,
+            failure,
+        )
+    };
+
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
         .bind(&payload.email)
         .fetch_optional(&pool)
@@ -212,66 +256,195 @@ pub async fn login(
         .map_err(|e| {
             tracing::error!("Database error: {:?}", e);
             AppError::InternalServerError
-        })?
-        .ok_or(AppError::Unauthorized)?;
+        })?;
 
-    utils::verify_password(&user.password_hash, &payload.password)
-        .map_err(|_| AppError::Unauthorized)?;
+    let Some(user) = user else {
+        record(None, Some(LoginFailure::UnknownAccount))
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+        return Err(AppError::Unauthorized);
+    };
+
+    // A locked account doesn't get its password checked at all
+    if let Some(locked_until) = user.locked_until.filter(|until| *until > Utc::now()) {
+        record(Some(user.id), Some(LoginFailure::AccountLocked))
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+        return Err(AppError::TooManyRequests(lockout::seconds_until(
+            locked_until,
+        )));
+    }
+
+    if utils::verify_password(&user.password_hash, &payload.password).is_err() {
+        record(Some(user.id), Some(LoginFailure::InvalidPassword))
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+        register_sign_in_failure(&pool, &settings, &email_service, &user).await?;
+        return Err(AppError::Unauthorized);
+    }
 
     // Check if email is verified
     if !user.email_verified {
+        record(Some(user.id), Some(LoginFailure::EmailNotVerified))
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
         return Err(AppError::UnprocessableEntity(
             "Please verify your email before logging in".to_string(),
         ));
     }
 
-    let token = jwt::create_token(user.id, &settings.jwt_secret)
+    // With 2FA enabled the password alone isn't enough: hand out a challenge token
+    // that the second step exchanges, together with a code, for a session
+    if user.totp_enabled_at.is_some() {
+        let challenge = issue_two_factor_challenge(&pool, user.id).await?;
+        return Ok(ApiResponse::success(challenge).into_response());
+    }
+
+    let response = complete_sign_in(&pool, &settings, user, &headers, &ip_address).await?;
+
+    Ok(ApiResponse::success(response).into_response())
+}
+
+/// POST /api/auth/sign-in/2fa
+/// Second sign-in step for accounts with 2FA: exchange the challenge token and a code
+/// (or a recovery code) for an access token and refresh token
+pub async fn verify_two_factor(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(email_service): State<EmailService>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<TwoFactorVerifyRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let ip_address = addr.ip().to_string();
+
+    if let Some(retry_after) = lockout::ip_retry_after(&pool, &ip_address, &settings)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    {
+        return Err(AppError::TooManyRequests(retry_after));
+    }
+
+    let challenge = sqlx::query_as::<_, AuthToken>(
+        "SELECT * FROM auth_tokens WHERE token = $1 AND token_type = 'two_factor_challenge' AND expires_at > NOW() AND used_at IS NULL",
+    )
+    .bind(&payload.challenge_token)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::Unauthorized)?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(challenge.user_id)
+        .fetch_one(&pool)
+        .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    Ok(ApiResponse::success(AuthResponse {
-        token,
-        user: UserResponse::from(user),
-    }))
+    // Wrong codes count towards the same lockout as wrong passwords
+    if let Some(locked_until) = user.locked_until.filter(|until| *until > Utc::now()) {
+        lockout::record_attempt(
+            &pool,
+            Some(user.id),
+            &user.email,
+            &ip_address,
+            &headers,
+            Some(LoginFailure::AccountLocked),
+        )
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+        return Err(AppError::TooManyRequests(lockout::seconds_until(
+            locked_until,
+        )));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let valid = totp::verify_second_factor(&mut tx, user.id, &payload.code)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to verify two-factor code: {:?}", e);
+            AppError::InternalServerError
+        })?;
+
+    if !valid {
+        drop(tx);
+        lockout::record_attempt(
+            &pool,
+            Some(user.id),
+            &user.email,
+            &ip_address,
+            &headers,
+            Some(LoginFailure::InvalidTwoFactorCode),
+        )
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+        register_sign_in_failure(&pool, &settings, &email_service, &user).await?;
+        return Err(AppError::Unauthorized);
+    }
+
+    // A challenge can only complete one sign-in
+    let consumed =
+        sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE id = $1 AND used_at IS NULL")
+            .bind(challenge.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+    if consumed.rows_affected() == 0 {
+        return Err(AppError::Unauthorized);
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let response = complete_sign_in(&pool, &settings, user, &headers, &ip_address).await?;
+
+    Ok(ApiResponse::success(response))
 }
 
-/// POST /api/auth/forgot-password
-/// Request password reset email
-pub async fn forgot_password(
+/// POST /api/auth/magic-link
+/// Email a one-time sign-in link, for readers who would rather not use a password
+pub async fn request_magic_link(
     State(pool): State<PgPool>,
     State(email_service): State<EmailService>,
-    Json(payload): Json<ForgotPasswordRequest>,
+    Json(payload): Json<MagicLinkRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     payload
         .validate()
         .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
 
-    // Find user
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
-        .bind(&payload.email)
-        .fetch_optional(&pool)
-        .await
-        .map_err(|_| AppError::InternalServerError)?;
+    // Unverified accounts verify their email first: whoever signed up with an address
+    // they don't own mustn't end up sharing the account with its real owner
+    let user =
+        sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1 AND email_verified = true")
+            .bind(&payload.email)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
 
     // Always return success to prevent email enumeration
     let Some(user) = user else {
         return Ok(ApiResponse::ok(
-            "If an account exists, a password reset email has been sent.".to_string(),
+            "If an account exists, a sign-in link has been sent.".to_string(),
         ));
     };
 
-    // Invalidate existing reset tokens
-    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE user_id = $1 AND token_type = 'password_reset' AND used_at IS NULL")
+    // Only the latest link works
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE user_id = $1 AND token_type = 'magic_link' AND used_at IS NULL")
         .bind(user.id)
         .execute(&pool)
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    // Generate reset token (expires in 1 hour)
     let token = utils::generate_secure_token();
-    let expires_at = Utc::now() + Duration::hours(1);
+    let expires_at = Utc::now() + Duration::minutes(MAGIC_LINK_TTL_MINUTES);
 
     sqlx::query(
-        "INSERT INTO auth_tokens (user_id, token, token_type, expires_at) VALUES ($1, $2, 'password_reset', $3)",
+        "INSERT INTO auth_tokens (user_id, token, token_type, expires_at) VALUES ($1, $2, 'magic_link', $3)",
     )
     .bind(user.id)
     .bind(&token)
@@ -280,32 +453,37 @@ pub async fn forgot_password(
     .await
     .map_err(|_| AppError::InternalServerError)?;
 
-    // Send reset email
     if let Err(e) = email_service
-        .send_password_reset_email(&user.email, &token)
+        .send_magic_link_email(&user.email, &user.username, &token)
         .await
     {
-        tracing::error!("Failed to send password reset email: {:?}", e);
+        tracing::error!("Failed to send magic link email: {:?}", e);
     }
 
     Ok(ApiResponse::ok(
-        "If an account exists, a password reset email has been sent.".to_string(),
+        "If an account exists, a sign-in link has been sent.".to_string(),
     ))
 }
 
-/// POST /api/auth/reset-password
-/// Reset password with token
-pub async fn reset_password(
+/// POST /api/auth/magic-link/verify
+/// Sign in with the token from a magic link. Returns the same response as sign-in,
+/// including the 2FA challenge for accounts that have it enabled.
+pub async fn verify_magic_link(
     State(pool): State<PgPool>,
-    Json(payload): Json<ResetPasswordRequest>,
+    State(settings): State<Settings>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<MagicLinkVerifyRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    payload
-        .validate()
-        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+    let ip_address = addr.ip().to_string();
 
-    // Find valid token
-    let auth_token = sqlx::query_as::<_, AuthToken>(
-        "SELECT * FROM auth_tokens WHERE token = $1 AND token_type = 'password_reset' AND expires_at > NOW() AND used_at IS NULL",
+    // Use the token up in the same statement that finds it, so it can't sign in twice
+    let user_id: Uuid = sqlx::query_scalar(
+        r#"
+        UPDATE auth_tokens SET used_at = NOW()
+        WHERE token = $1 AND token_type = 'magic_link' AND expires_at > NOW() AND used_at IS NULL
+        RETURNING user_id
+        "#,
     )
     .bind(&payload.token)
     .fetch_optional(&pool)
@@ -313,53 +491,1480 @@ pub async fn reset_password(
     .map_err(|_| AppError::InternalServerError)?
     .ok_or(AppError::BadRequest("Invalid or expired token".to_string()))?;
 
-    // Hash new password
-    let password_hash =
-        utils::hash_password(&payload.new_password).map_err(|_| AppError::InternalServerError)?;
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
 
-    // Update password and mark token as used
-    let mut tx = pool
-        .begin()
+    if let Some(locked_until) = user.locked_until.filter(|until| *until > Utc::now()) {
+        lockout::record_attempt(
+            &pool,
+            Some(user.id),
+            &user.email,
+            &ip_address,
+            &headers,
+            Some(LoginFailure::AccountLocked),
+        )
         .await
         .map_err(|_| AppError::InternalServerError)?;
+        return Err(AppError::TooManyRequests(lockout::seconds_until(
+            locked_until,
+        )));
+    }
 
-    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE id = $1")
-        .bind(auth_token.id)
-        .execute(&mut *tx)
+    // The link stands in for the password, not for the second factor
+    if user.totp_enabled_at.is_some() {
+        let challenge = issue_two_factor_challenge(&pool, user.id).await?;
+        return Ok(ApiResponse::success(challenge).into_response());
+    }
+
+    let response = complete_sign_in(&pool, &settings, user, &headers, &ip_address).await?;
+
+    Ok(ApiResponse::success(response).into_response())
+}
+
+/// Create the challenge token a 2FA user exchanges, together with a code, for a session
+async fn issue_two_factor_challenge(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<TwoFactorChallengeResponse, AppError> {
+    let challenge_token = utils::generate_secure_token();
+    let expires_at = Utc::now() + Duration::minutes(totp::CHALLENGE_TTL_MINUTES);
+
+    sqlx::query(
+        "INSERT INTO auth_tokens (user_id, token, token_type, expires_at) VALUES ($1, $2, 'two_factor_challenge', $3)",
+    )
+    .bind(user_id)
+    .bind(&challenge_token)
+    .bind(expires_at)
+    .execute(pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(TwoFactorChallengeResponse {
+        two_factor_required: true,
+        challenge_token,
+        expires_in: totp::CHALLENGE_TTL_MINUTES * 60,
+    })
+}
+
+/// Count a wrong password or 2FA code against an account, emailing the user if it gets locked
+async fn register_sign_in_failure(
+    pool: &PgPool,
+    settings: &Settings,
+    email_service: &EmailService,
+    user: &User,
+) -> Result<(), AppError> {
+    let locked_until = lockout::register_failure(pool, user.id, settings)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to register login failure: {:?}", e);
+            AppError::InternalServerError
+        })?;
+
+    if let Some(locked_until) = locked_until {
+        tracing::warn!("Account {} locked until {}", user.id, locked_until);
+
+        if let Err(e) = email_service
+            .send_account_locked_email(&user.email, &user.username, locked_until)
+            .await
+        {
+            tracing::error!("Failed to send account locked email: {:?}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a session for a fully authenticated user, clear their lockout state and
+/// record the successful sign-in
+async fn complete_sign_in(
+    pool: &PgPool,
+    settings: &Settings,
+    user: User,
+    headers: &HeaderMap,
+    ip_address: &str,
+) -> Result<AuthResponse, AppError> {
+    let (token, refresh_token) = session::start(
+        pool,
+        user.id,
+        user.role,
+        &settings.jwt_secret,
+        headers,
+        Some(ip_address.to_string()),
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to start session: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    lockout::reset(pool, user.id)
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
-        .bind(&password_hash)
-        .bind(auth_token.user_id)
-        .execute(&mut *tx)
+    // Signing in during the grace period keeps the account
+    if deletion::cancel(pool, user.id)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    {
+        tracing::info!("Account deletion cancelled for {}", user.id);
+    }
+    lockout::record_attempt(pool, Some(user.id), &user.email, ip_address, headers, None)
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    tx.commit()
+    Ok(AuthResponse {
+        token,
+        refresh_token,
+        user: UserResponse::from(user),
+    })
+}
+
+/// GET /api/auth/oauth/providers
+/// Names of the OAuth providers users can sign in with
+pub async fn get_oauth_providers(State(settings): State<Settings>) -> impl IntoResponse {
+    let providers: Vec<String> = settings
+        .oauth_providers
+        .iter()
+        .map(|p| p.name.clone())
+        .collect();
+
+    ApiResponse::success(providers)
+}
+
+/// GET /api/auth/oauth/{provider}/authorize
+/// Start signing in with an OAuth provider: returns the URL to send the user to.
+/// The provider redirects back to the frontend, which finishes at the callback endpoint.
+pub async fn oauth_authorize(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(oauth_client): State<OAuthClient>,
+    Path(provider): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let authorization_url =
+        start_oauth_flow(&pool, &settings, &oauth_client, &provider, None).await?;
+
+    Ok(ApiResponse::success(OAuthAuthorizeResponse {
+        authorization_url,
+    }))
+}
+
+/// POST /api/auth/oauth/{provider}/callback
+/// Finish an OAuth sign-in with the code and state the provider redirected back with.
+/// Signs in the account linked to the provider identity; otherwise links the account
+/// with the same email (trusted providers only) or creates a new one.
+#[allow(clippy::too_many_arguments)]
+pub async fn oauth_callback(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(oauth_client): State<OAuthClient>,
+    State(email_service): State<EmailService>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(provider): Path<String>,
+    Json(payload): Json<OAuthCallbackRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let provider = settings
+        .oauth_provider(&provider)
+        .ok_or(AppError::NotFound("Unknown sign-in provider".to_string()))?;
+    let ip_address = addr.ip().to_string();
+
+    let (code_verifier, nonce) = take_oauth_state(&pool, provider, &payload.state, None).await?;
+
+    let identity = oauth_client
+        .authenticate(provider, &payload.code, &code_verifier, &nonce)
+        .await
+        .map_err(|e| {
+            tracing::warn!("Sign-in with {} failed: {:?}", provider.name, e);
+            AppError::Unauthorized
+        })?;
+
+    let linked_user_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        UPDATE user_identities SET email = $3, last_used_at = NOW()
+        WHERE provider = $1 AND subject = $2
+        RETURNING user_id
+        "#,
+    )
+    .bind(&provider.name)
+    .bind(&identity.subject)
+    .bind(&identity.email)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    let user = match linked_user_id {
+        Some(user_id) => sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .map_err(|_| AppError::InternalServerError)?,
+        None => link_or_create_oauth_user(&pool, &email_service, provider, &identity).await?,
+    };
+
+    if let Some(locked_until) = user.locked_until.filter(|until| *until > Utc::now()) {
+        lockout::record_attempt(
+            &pool,
+            Some(user.id),
+            &user.email,
+            &ip_address,
+            &headers,
+            Some(LoginFailure::AccountLocked),
+        )
         .await
         .map_err(|_| AppError::InternalServerError)?;
+        return Err(AppError::TooManyRequests(lockout::seconds_until(
+            locked_until,
+        )));
+    }
 
-    Ok(ApiResponse::ok("Password reset successfully".to_string()))
+    if !user.email_verified {
+        lockout::record_attempt(
+            &pool,
+            Some(user.id),
+            &user.email,
+            &ip_address,
+            &headers,
+            Some(LoginFailure::EmailNotVerified),
+        )
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+        return Err(AppError::UnprocessableEntity(
+            "Please verify your email before logging in".to_string(),
+        ));
+    }
+
+    // The provider stands in for the password, not for the second factor
+    if user.totp_enabled_at.is_some() {
+        let challenge = issue_two_factor_challenge(&pool, user.id).await?;
+        return Ok(ApiResponse::success(challenge).into_response());
+    }
+
+    let response = complete_sign_in(&pool, &settings, user, &headers, &ip_address).await?;
+
+    Ok(ApiResponse::success(response).into_response())
 }
 
-/// GET /api/auth/me
-/// Get current user profile
-pub async fn get_me(
+/// GET /api/auth/me/identities
+/// OAuth provider accounts linked to the current user
+pub async fn get_identities(
     State(pool): State<PgPool>,
     claims: jwt::Claims,
 ) -> Result<impl IntoResponse, AppError> {
-    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
-        .bind(claims.sub)
-        .fetch_optional(&pool)
+    let identities = sqlx::query_as::<_, IdentityResponse>(
+        r#"
+        SELECT provider, email, created_at, last_used_at
+        FROM user_identities
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(identities))
+}
+
+/// POST /api/auth/me/identities/{provider}
+/// Start linking an OAuth provider to the current user: returns the URL to send the
+/// user to. The provider redirects back as for sign-in, and the frontend finishes at
+/// the identity callback endpoint, still signed in.
+pub async fn link_identity(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(oauth_client): State<OAuthClient>,
+    claims: jwt::Claims,
+    Path(provider): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let authorization_url =
+        start_oauth_flow(&pool, &settings, &oauth_client, &provider, Some(claims.sub)).await?;
+
+    Ok(ApiResponse::success(OAuthAuthorizeResponse {
+        authorization_url,
+    }))
+}
+
+/// POST /api/auth/me/identities/{provider}/callback
+/// Finish linking an OAuth provider with the code and state the provider redirected
+/// back with. The state must have been started by the same signed-in user, so nobody
+/// can get their provider account linked to someone else's by sending them a link.
+pub async fn link_identity_callback(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(oauth_client): State<OAuthClient>,
+    claims: jwt::Claims,
+    Path(provider): Path<String>,
+    Json(payload): Json<OAuthCallbackRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let provider = settings
+        .oauth_provider(&provider)
+        .ok_or(AppError::NotFound("Unknown sign-in provider".to_string()))?;
+
+    let (code_verifier, nonce) =
+        take_oauth_state(&pool, provider, &payload.state, Some(claims.sub)).await?;
+
+    let identity = oauth_client
+        .authenticate(provider, &payload.code, &code_verifier, &nonce)
         .await
         .map_err(|e| {
+            tracing::warn!("Linking {} failed: {:?}", provider.name, e);
+            AppError::Unauthorized
+        })?;
+
+    let linked = sqlx::query_as::<_, IdentityResponse>(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email, last_used_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        RETURNING provider, email, created_at, last_used_at
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(&provider.name)
+    .bind(&identity.subject)
+    .bind(&identity.email)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("duplicate key value") {
+            AppError::Conflict(format!(
+                "A {} account is already linked to this user, or this one belongs to another user",
+                provider.name
+            ))
+        } else {
             tracing::error!("Database error: {:?}", e);
             AppError::InternalServerError
-        })?
-        .ok_or(AppError::NotFound("User not found".to_string()))?;
+        }
+    })?;
 
-    Ok(ApiResponse::success(UserResponse::from(user)))
+    Ok(ApiResponse::success_with_message(
+        format!("{} account linked", provider.name),
+        linked,
+    ))
+}
+
+/// DELETE /api/auth/me/identities/{provider}
+/// Unlink an OAuth provider. Accounts created through a provider have a random
+/// password, which can be replaced through forgot-password.
+pub async fn unlink_identity(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(provider): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = sqlx::query("DELETE FROM user_identities WHERE user_id = $1 AND provider = $2")
+        .bind(claims.sub)
+        .bind(&provider)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Identity not found".to_string()));
+    }
+
+    Ok(ApiResponse::ok(format!("{} account unlinked", provider)))
+}
+
+/// Remember a new authorization request (state, PKCE code verifier and nonce) and
+/// build the provider URL for it
+async fn start_oauth_flow(
+    pool: &PgPool,
+    settings: &Settings,
+    oauth_client: &OAuthClient,
+    provider: &str,
+    link_user_id: Option<Uuid>,
+) -> Result<String, AppError> {
+    let provider = settings
+        .oauth_provider(provider)
+        .ok_or(AppError::NotFound("Unknown sign-in provider".to_string()))?;
+
+    let state = utils::generate_secure_token();
+    let code_verifier = utils::generate_secure_token();
+    let nonce = utils::generate_secure_token();
+
+    let authorization_url = oauth_client
+        .authorization_url(provider, &state, &code_verifier, &nonce)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to reach OAuth provider {}: {:?}", provider.name, e);
+            AppError::InternalServerError
+        })?;
+
+    // Forget requests that were never finished
+    sqlx::query("DELETE FROM oauth_states WHERE expires_at <= NOW()")
+        .execute(pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO oauth_states (state, provider, code_verifier, nonce, link_user_id, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(&state)
+    .bind(&provider.name)
+    .bind(&code_verifier)
+    .bind(&nonce)
+    .bind(link_user_id)
+    .bind(Utc::now() + Duration::minutes(oauth::STATE_TTL_MINUTES))
+    .execute(pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(authorization_url)
+}
+
+/// Use up a pending authorization request, returning its PKCE code verifier and nonce.
+/// Each state can only be used once, and only for what it was started for: sign-in
+/// states have no user, linking states belong to the user who started them.
+async fn take_oauth_state(
+    pool: &PgPool,
+    provider: &OAuthProviderConfig,
+    state: &str,
+    link_user_id: Option<Uuid>,
+) -> Result<(String, String), AppError> {
+    sqlx::query_as(
+        r#"
+        DELETE FROM oauth_states
+        WHERE state = $1 AND provider = $2 AND expires_at > NOW()
+            AND link_user_id IS NOT DISTINCT FROM $3
+        RETURNING code_verifier, nonce
+        "#,
+    )
+    .bind(state)
+    .bind(&provider.name)
+    .bind(link_user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::BadRequest(
+        "Invalid or expired sign-in request".to_string(),
+    ))
+}
+
+/// Find or create the account for a provider identity that isn't linked yet.
+///
+/// An existing account with the same email is only linked when a trusted provider
+/// says the email is verified; otherwise anyone could take over an account by
+/// putting its email on an account at a provider that doesn't check it.
+async fn link_or_create_oauth_user(
+    pool: &PgPool,
+    email_service: &EmailService,
+    provider: &OAuthProviderConfig,
+    identity: &oauth::ProviderIdentity,
+) -> Result<User, AppError> {
+    let email = identity.email.as_deref().ok_or_else(|| {
+        AppError::UnprocessableEntity(format!(
+            "Your {} account did not share an email address",
+            provider.name
+        ))
+    })?;
+    let email_verified = provider.trusted_email && identity.email_verified;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let existing =
+        sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = LOWER($1) FOR UPDATE")
+            .bind(email)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+    // Nobody knows the random password of an account created here; a real one can
+    // be set through forgot-password
+    let random_password_hash = utils::hash_password(&utils::generate_secure_token())
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let (user, created) = match existing {
+        Some(_) if !email_verified => {
+            return Err(AppError::Conflict(format!(
+                "An account with this email already exists. Sign in with your password and link {} from your account settings.",
+                provider.name
+            )));
+        }
+        // Whoever signed up with this email never proved they own it, so their
+        // password must not keep working on an account the email owner now controls
+        Some(user) if !user.email_verified => {
+            let user = sqlx::query_as::<_, User>(
+                "UPDATE users SET email_verified = true, password_hash = $2, updated_at = NOW() WHERE id = $1 RETURNING *",
+            )
+            .bind(user.id)
+            .bind(&random_password_hash)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+            (user, false)
+        }
+        Some(user) => (user, false),
+        None => {
+            let source = identity
+                .preferred_username
+                .as_deref()
+                .unwrap_or_else(|| email.split('@').next().unwrap_or_default());
+            let username = oauth::available_username(&mut tx, source)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to pick a username: {:?}", e);
+                    AppError::InternalServerError
+                })?;
+
+            let user = sqlx::query_as::<_, User>(
+                "INSERT INTO users (id, username, email, password_hash, email_verified) VALUES ($1, $2, $3, $4, $5) RETURNING *",
+            )
+            .bind(Uuid::new_v4())
+            .bind(&username)
+            .bind(email)
+            .bind(&random_password_hash)
+            .bind(email_verified)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e: sqlx::Error| {
+                if e.to_string().contains("duplicate key value") {
+                    AppError::Conflict("Username or Email already exists".to_string())
+                } else {
+                    tracing::error!("Database error: {:?}", e);
+                    AppError::InternalServerError
+                }
+            })?;
+            (user, true)
+        }
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_identities (user_id, provider, subject, email, last_used_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        "#,
+    )
+    .bind(user.id)
+    .bind(&provider.name)
+    .bind(&identity.subject)
+    .bind(email)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("duplicate key value") {
+            AppError::Conflict(format!(
+                "This account already has a different {} account linked",
+                provider.name
+            ))
+        } else {
+            tracing::error!("Database error: {:?}", e);
+            AppError::InternalServerError
+        }
+    })?;
+
+    // New accounts from untrusted providers verify their email like any other signup
+    let verification_token = if created && !user.email_verified {
+        let token = utils::generate_secure_token();
+        sqlx::query(
+            "INSERT INTO auth_tokens (user_id, token, token_type, expires_at) VALUES ($1, $2, 'email_verification', $3)",
+        )
+        .bind(user.id)
+        .bind(&token)
+        .bind(Utc::now() + Duration::hours(24))
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+        Some(token)
+    } else {
+        None
+    };
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if let Some(token) = verification_token {
+        if let Err(e) = email_service
+            .send_verification_email(&user.email, &token)
+            .await
+        {
+            tracing::error!("Failed to send verification email: {:?}", e);
+        }
+    } else if created {
+        if let Err(e) = email_service
+            .send_welcome_email(&user.email, &user.username)
+            .await
+        {
+            tracing::error!("Failed to send welcome email: {:?}", e);
+        }
+    }
+
+    tracing::info!(
+        "{} identity linked to user {} ({})",
+        provider.name,
+        user.id,
+        if created {
+            "new account"
+        } else {
+            "existing account"
+        }
+    );
+
+    Ok(user)
+}
+
+/// POST /api/auth/2fa/enroll
+/// Start enrolling an authenticator app. 2FA is only enabled once a code is confirmed.
+pub async fn enroll_two_factor(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    // Starting over replaces any secret from an unfinished enrollment
+    let secret = totp::generate_secret();
+    sqlx::query("UPDATE users SET totp_secret = $2, totp_last_step = NULL WHERE id = $1")
+        .bind(user.id)
+        .bind(&secret)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(TwoFactorEnrollResponse {
+        otpauth_uri: totp::otpauth_uri(&user.email, &secret),
+        secret,
+    }))
+}
+
+/// POST /api/auth/2fa/confirm
+/// Confirm enrollment with a code from the authenticator app; enables 2FA and returns
+/// the recovery codes
+pub async fn confirm_two_factor(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let (secret, enabled_at): (Option<String>, Option<chrono::DateTime<Utc>>) =
+        sqlx::query_as("SELECT totp_secret, totp_enabled_at FROM users WHERE id = $1 FOR UPDATE")
+            .bind(claims.sub)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?
+            .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    if enabled_at.is_some() {
+        return Err(AppError::Conflict(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    let secret = secret.ok_or(AppError::BadRequest(
+        "Two-factor enrollment has not been started".to_string(),
+    ))?;
+
+    let step = totp::verify_code(&secret, &payload.code, None).ok_or(
+        AppError::UnprocessableEntity("Invalid two-factor code".to_string()),
+    )?;
+
+    sqlx::query("UPDATE users SET totp_enabled_at = NOW(), totp_last_step = $2 WHERE id = $1")
+        .bind(claims.sub)
+        .bind(step)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let recovery_codes = totp::replace_recovery_codes(&mut tx, claims.sub)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(RecoveryCodesResponse {
+        recovery_codes,
+    }))
+}
+
+/// POST /api/auth/2fa/recovery-codes
+/// Replace the recovery codes with a new set; requires a current code
+pub async fn regenerate_recovery_codes(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    require_second_factor(&mut tx, claims.sub, &payload.code).await?;
+
+    let recovery_codes = totp::replace_recovery_codes(&mut tx, claims.sub)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(RecoveryCodesResponse {
+        recovery_codes,
+    }))
+}
+
+/// POST /api/auth/2fa/disable
+/// Turn off 2FA; requires a current code or a recovery code
+pub async fn disable_two_factor(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    require_second_factor(&mut tx, claims.sub, &payload.code).await?;
+
+    sqlx::query(
+        "UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL WHERE id = $1",
+    )
+    .bind(claims.sub)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Pending sign-in challenges no longer lead anywhere
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE user_id = $1 AND token_type = 'two_factor_challenge' AND used_at IS NULL")
+        .bind(claims.sub)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::ok(
+        "Two-factor authentication disabled".to_string(),
+    ))
+}
+
+/// Fail unless 2FA is enabled and the code (or a recovery code) is valid
+async fn require_second_factor(
+    conn: &mut sqlx::PgConnection,
+    user_id: Uuid,
+    code: &str,
+) -> Result<(), AppError> {
+    let enabled: bool =
+        sqlx::query_scalar("SELECT totp_enabled_at IS NOT NULL FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|_| AppError::InternalServerError)?
+            .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    if !enabled {
+        return Err(AppError::UnprocessableEntity(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+
+    let valid = totp::verify_second_factor(conn, user_id, code)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if !valid {
+        return Err(AppError::UnprocessableEntity(
+            "Invalid two-factor code".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// POST /api/auth/refresh
+/// Exchange a refresh token for a new access token, rotating the refresh token
+pub async fn refresh(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    Json(payload): Json<RefreshTokenRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let new_refresh_token = utils::generate_secure_token();
+    let expires_at = Utc::now() + Duration::days(session::REFRESH_TOKEN_TTL_DAYS);
+
+    // Rotate atomically so two concurrent refreshes can't both succeed
+    let rotated = sqlx::query_as::<_, Session>(
+        r#"
+        UPDATE sessions SET
+            previous_refresh_token = refresh_token,
+            refresh_token = $1,
+            expires_at = $2,
+            last_used_at = NOW()
+        WHERE refresh_token = $3 AND revoked_at IS NULL AND expires_at > NOW()
+        RETURNING *
+        "#,
+    )
+    .bind(&new_refresh_token)
+    .bind(expires_at)
+    .bind(&payload.refresh_token)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Database error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let Some(rotated) = rotated else {
+        // A rotated-out token being presented again means it was stolen:
+        // revoke the whole session so neither party can keep using it
+        let reused = sqlx::query(
+            "UPDATE sessions SET revoked_at = NOW() WHERE previous_refresh_token = $1 AND revoked_at IS NULL",
+        )
+        .bind(&payload.refresh_token)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+        if reused.rows_affected() > 0 {
+            tracing::warn!("Refresh token reuse detected, session revoked");
+        }
+
+        return Err(AppError::Unauthorized);
+    };
+
+    let role: Role = sqlx::query_scalar("SELECT role FROM users WHERE id = $1")
+        .bind(rotated.user_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let token = jwt::create_token(rotated.user_id, rotated.id, role, &settings.jwt_secret)
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(TokenResponse {
+        token,
+        refresh_token: new_refresh_token,
+    }))
+}
+
+/// POST /api/auth/logout
+/// Revoke the current session
+pub async fn logout(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+        .bind(claims.sid)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::ok("Logged out".to_string()))
+}
+
+/// GET /api/auth/me/security-log
+/// List recent sign-ins and failed attempts on the current user's account, newest first
+pub async fn get_security_log(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Query(filter): Query<SecurityLogFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+
+    // Fetch one extra row to know whether there is a next page
+    let mut attempts = sqlx::query_as::<_, LoginAttemptResponse>(
+        r#"
+        SELECT id, success, failure_reason, ip_address, user_agent, created_at
+        FROM login_attempts
+        WHERE user_id = $1
+          AND ($3::TIMESTAMPTZ IS NULL OR (created_at, id) < ($3, $4))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(limit + 1)
+    .bind(cursor.as_ref().map(|c| c.timestamp))
+    .bind(cursor.as_ref().map(|c| c.id))
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Database error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let pagination =
+        Pagination::from_rows(&mut attempts, limit, |a| Cursor::new(a.created_at, a.id));
+
+    Ok(ApiResponse::paginated(attempts, pagination))
+}
+
+/// GET /api/auth/sessions
+/// List the current user's active sessions
+pub async fn get_sessions(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let sessions = sqlx::query_as::<_, Session>(
+        r#"
+        SELECT * FROM sessions
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY last_used_at DESC
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Database error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let response: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|s| SessionResponse {
+            current: s.id == claims.sid,
+            id: s.id,
+            user_agent: s.user_agent,
+            ip_address: s.ip_address,
+            created_at: s.created_at,
+            last_used_at: s.last_used_at,
+            expires_at: s.expires_at,
+        })
+        .collect();
+
+    Ok(ApiResponse::success(response))
+}
+
+/// DELETE /api/auth/sessions
+/// Revoke all sessions except the current one
+pub async fn revoke_other_sessions(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let revoked = session::revoke_all(&pool, claims.sub, Some(claims.sid))
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::ok(format!("{} session(s) revoked", revoked)))
+}
+
+/// DELETE /api/auth/sessions/:id
+/// Revoke a single session (sign out a device)
+pub async fn revoke_session(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = sqlx::query(
+        "UPDATE sessions SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(id)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Session not found".to_string()));
+    }
+
+    Ok(ApiResponse::ok("Session revoked".to_string()))
+}
+
+/// POST /api/auth/forgot-password
+/// Request password reset email
+pub async fn forgot_password(
+    State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    // Find user
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+        .bind(&payload.email)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Always return success to prevent email enumeration
+    let Some(user) = user else {
+        return Ok(ApiResponse::ok(
+            "If an account exists, a password reset email has been sent.".to_string(),
+        ));
+    };
+
+    // Invalidate existing reset tokens
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE user_id = $1 AND token_type = 'password_reset' AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Generate reset token (expires in 1 hour)
+    let token = utils::generate_secure_token();
+    let expires_at = Utc::now() + Duration::hours(1);
+
+    sqlx::query(
+        "INSERT INTO auth_tokens (user_id, token, token_type, expires_at) VALUES ($1, $2, 'password_reset', $3)",
+    )
+    .bind(user.id)
+    .bind(&token)
+    .bind(expires_at)
+    .execute(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    // Send reset email
+    if let Err(e) = email_service
+        .send_password_reset_email(&user.email, &token)
+        .await
+    {
+        tracing::error!("Failed to send password reset email: {:?}", e);
+    }
+
+    Ok(ApiResponse::ok(
+        "If an account exists, a password reset email has been sent.".to_string(),
+    ))
+}
+
+/// POST /api/auth/reset-password
+/// Reset password with token
+pub async fn reset_password(
+    State(pool): State<PgPool>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    // Find valid token
+    let auth_token = sqlx::query_as::<_, AuthToken>(
+        "SELECT * FROM auth_tokens WHERE token = $1 AND token_type = 'password_reset' AND expires_at > NOW() AND used_at IS NULL",
+    )
+    .bind(&payload.token)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::BadRequest("Invalid or expired token".to_string()))?;
+
+    // Hash new password
+    let password_hash =
+        utils::hash_password(&payload.new_password).map_err(|_| AppError::InternalServerError)?;
+
+    // Update password and mark token as used
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE id = $1")
+        .bind(auth_token.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+        .bind(&password_hash)
+        .bind(auth_token.user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::ok("Password reset successfully".to_string()))
+}
+
+/// POST /api/auth/change-password
+/// Change the current user's password; signs out every other session
+pub async fn change_password(
+    State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
+    claims: jwt::Claims,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    utils::verify_password(&user.password_hash, &payload.current_password)
+        .map_err(|_| AppError::UnprocessableEntity("Current password is incorrect".to_string()))?;
+
+    if payload.new_password == payload.current_password {
+        return Err(AppError::UnprocessableEntity(
+            "New password must be different from the current one".to_string(),
+        ));
+    }
+
+    let password_hash =
+        utils::hash_password(&payload.new_password).map_err(|_| AppError::InternalServerError)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+        .bind(&password_hash)
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Outstanding reset links were issued for the old password
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE user_id = $1 AND token_type = 'password_reset' AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    session::revoke_all(&mut *tx, user.id, Some(claims.sid))
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if let Err(e) = email_service
+        .send_password_changed_email(&user.email, &user.username)
+        .await
+    {
+        tracing::error!("Failed to send password changed email: {:?}", e);
+    }
+
+    Ok(ApiResponse::ok("Password changed".to_string()))
+}
+
+/// POST /api/auth/change-email
+/// Start changing the current user's email; a confirmation link goes to the new address
+/// and the email only changes once it is confirmed
+pub async fn change_email(
+    State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
+    claims: jwt::Claims,
+    Json(payload): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    utils::verify_password(&user.password_hash, &payload.password)
+        .map_err(|_| AppError::UnprocessableEntity("Password is incorrect".to_string()))?;
+
+    if payload.new_email == user.email {
+        return Err(AppError::UnprocessableEntity(
+            "This is already your email".to_string(),
+        ));
+    }
+
+    let taken: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
+        .bind(&payload.new_email)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if taken {
+        return Err(AppError::Conflict("Email already in use".to_string()));
+    }
+
+    let token = utils::generate_secure_token();
+    let expires_at = Utc::now() + Duration::hours(24);
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Only the most recent request can be confirmed
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE user_id = $1 AND token_type = 'email_change' AND used_at IS NULL")
+        .bind(user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query(
+        "INSERT INTO auth_tokens (user_id, token, token_type, new_email, expires_at) VALUES ($1, $2, 'email_change', $3, $4)",
+    )
+    .bind(user.id)
+    .bind(&token)
+    .bind(&payload.new_email)
+    .bind(expires_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if let Err(e) = email_service
+        .send_email_change_email(&payload.new_email, &token)
+        .await
+    {
+        tracing::error!("Failed to send email change confirmation: {:?}", e);
+    }
+
+    Ok(ApiResponse::ok(
+        "A confirmation link has been sent to the new address".to_string(),
+    ))
+}
+
+/// POST /api/auth/confirm-email-change
+/// Confirm an email change with the token from the link sent to the new address;
+/// the previous address gets a security notice
+pub async fn confirm_email_change(
+    State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
+    Json(payload): Json<ConfirmEmailChangeRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let auth_token = sqlx::query_as::<_, AuthToken>(
+        "SELECT * FROM auth_tokens WHERE token = $1 AND token_type = 'email_change' AND expires_at > NOW() AND used_at IS NULL",
+    )
+    .bind(&payload.token)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::BadRequest("Invalid or expired token".to_string()))?;
+
+    let new_email = auth_token
+        .new_email
+        .ok_or(AppError::BadRequest("Invalid or expired token".to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query("UPDATE auth_tokens SET used_at = NOW() WHERE id = $1")
+        .bind(auth_token.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let (old_email, username): (String, String) =
+        sqlx::query_as("SELECT email, username FROM users WHERE id = $1 FOR UPDATE")
+            .bind(auth_token.user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+    // Following the link proves the new address works
+    sqlx::query(
+        "UPDATE users SET email = $1, email_verified = true, updated_at = NOW() WHERE id = $2",
+    )
+    .bind(&new_email)
+    .bind(auth_token.user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("duplicate key value") {
+            AppError::Conflict("Email already in use".to_string())
+        } else {
+            tracing::error!("Database error: {:?}", e);
+            AppError::InternalServerError
+        }
+    })?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if let Err(e) = email_service
+        .send_email_changed_email(&old_email, &username, &new_email)
+        .await
+    {
+        tracing::error!("Failed to send email changed notice: {:?}", e);
+    }
+
+    Ok(ApiResponse::ok("Email changed".to_string()))
+}
+
+/// GET /api/auth/me
+/// Get current user profile
+pub async fn get_me(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Database error: {:?}", e);
+            AppError::InternalServerError
+        })?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    Ok(ApiResponse::success(UserResponse::from(user)))
+}
+
+/// PUT /api/auth/me
+/// Update current user's username, bio and image
+pub async fn update_me(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Json(payload): Json<UpdateProfile>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    // NULL leaves a column unchanged, an empty string clears bio/image
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users SET
+            username = COALESCE($1, username),
+            bio = CASE WHEN $2::TEXT IS NULL THEN bio ELSE NULLIF(TRIM($2), '') END,
+            image = CASE WHEN $3::TEXT IS NULL THEN image ELSE NULLIF(TRIM($3), '') END,
+            updated_at = NOW()
+        WHERE id = $4
+        RETURNING *
+        "#,
+    )
+    .bind(&payload.username)
+    .bind(&payload.bio)
+    .bind(&payload.image)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e: sqlx::Error| {
+        if e.to_string().contains("duplicate key value") {
+            AppError::Conflict("Username already exists".to_string())
+        } else {
+            tracing::error!("Database error: {:?}", e);
+            AppError::InternalServerError
+        }
+    })?
+    .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    Ok(ApiResponse::success_with_message(
+        "Profile updated".to_string(),
+        UserResponse::from(user),
+    ))
+}
+
+/// DELETE /api/auth/me
+/// Delete the current account after confirming the password (and 2FA code, if enabled).
+/// Every session is signed out and the data is removed once the grace period is over;
+/// signing in again before then cancels the deletion.
+pub async fn delete_me(
+    State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(email_service): State<EmailService>,
+    claims: jwt::Claims,
+    Json(payload): Json<DeleteAccountRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    utils::verify_password(&user.password_hash, &payload.password)
+        .map_err(|_| AppError::UnprocessableEntity("Password is incorrect".to_string()))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if user.totp_enabled_at.is_some() {
+        let code = payload
+            .code
+            .as_deref()
+            .ok_or(AppError::UnprocessableEntity(
+                "A two-factor code is required".to_string(),
+            ))?;
+        require_second_factor(&mut tx, user.id, code).await?;
+    }
+
+    let deletion_scheduled_for: chrono::DateTime<Utc> = sqlx::query_scalar(
+        r#"
+        UPDATE users SET deletion_scheduled_for = NOW() + make_interval(days => $2)
+        WHERE id = $1
+        RETURNING deletion_scheduled_for
+        "#,
+    )
+    .bind(user.id)
+    .bind(settings.deletion_grace_days as i32)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Database error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    session::revoke_all(&mut *tx, user.id, None)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if let Err(e) = email_service
+        .send_account_deletion_email(&user.email, &user.username, deletion_scheduled_for)
+        .await
+    {
+        tracing::error!("Failed to send account deletion email: {:?}", e);
+    }
+
+    Ok(ApiResponse::success_with_message(
+        "Account scheduled for deletion. Sign in again before then to cancel.".to_string(),
+        AccountDeletionResponse {
+            deletion_scheduled_for,
+        },
+    ))
 }
 
 /// GET /api/user/:id
@@ -12,21 +12,30 @@ use axum_extra::{
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::config::settings::Settings;
+use crate::{auth::Role, config::settings::Settings};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid,
+    /// Session this access token belongs to
+    pub sid: Uuid,
+    /// Role at issue time; refreshed from the database on every request.
+    /// Defaults to `user` for tokens issued before roles existed.
+    #[serde(default)]
+    pub role: Role,
     pub exp: i64,
     pub iat: i64,
 }
 
-pub fn create_token(user_id: Uuid, secret: &str) -> Result<String> {
+pub fn create_token(user_id: Uuid, session_id: Uuid, role: Role, secret: &str) -> Result<String> {
     let now = Utc::now();
     let claims = Claims {
         sub: user_id,
+        sid: session_id,
+        role,
         exp: (now + Duration::hours(1)).timestamp(),
         iat: now.timestamp(),
     };
@@ -42,6 +51,7 @@ impl<S> FromRequestParts<S> for Claims
 where
     S: Send + Sync,
     Settings: FromRef<S>,
+    PgPool: FromRef<S>,
 {
     type Rejection = axum::http::StatusCode;
 
@@ -60,6 +70,26 @@ where
         )
         .map_err(|_| axum::http::StatusCode::UNAUTHORIZED)?;
 
-        Ok(token.claims)
+        // Reject access tokens whose session was revoked (logout, device removal),
+        // and pick up the current role so demotions take effect immediately
+        let pool = PgPool::from_ref(state);
+        let role: Role = sqlx::query_scalar(
+            r#"
+            SELECT u.role FROM sessions s
+            JOIN users u ON s.user_id = u.id
+            WHERE s.id = $1 AND s.user_id = $2 AND s.revoked_at IS NULL
+            "#,
+        )
+        .bind(token.claims.sid)
+        .bind(token.claims.sub)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| axum::http::StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(axum::http::StatusCode::UNAUTHORIZED)?;
+
+        Ok(Claims {
+            role,
+            ..token.claims
+        })
     }
 }
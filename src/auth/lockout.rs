@@ -0,0 +1,141 @@
+use anyhow::Result;
+use axum::http::{header::USER_AGENT, HeaderMap};
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::{auth::LoginFailure, config::settings::Settings};
+
+/// Record a sign-in attempt in the audit log
+pub async fn record_attempt<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Option<Uuid>,
+    email: &str,
+    ip_address: &str,
+    headers: &HeaderMap,
+    failure: Option<LoginFailure>,
+) -> Result<()> {
+    let user_agent = headers.get(USER_AGENT).and_then(|v| v.to_str().ok());
+
+    sqlx::query(
+        r#"
+        INSERT INTO login_attempts (user_id, email, ip_address, user_agent, success, failure_reason)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(user_id)
+    .bind(email)
+    .bind(ip_address)
+    .bind(user_agent)
+    .bind(failure.is_none())
+    .bind(failure)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Seconds until an IP may try again, if it has too many recent failed sign-ins.
+/// Only wrong passwords, wrong 2FA codes and unknown accounts count; attempts on
+/// locked accounts don't.
+pub async fn ip_retry_after(
+    pool: &PgPool,
+    ip_address: &str,
+    settings: &Settings,
+) -> Result<Option<u64>> {
+    let (failures, oldest): (i64, Option<DateTime<Utc>>) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*), MIN(created_at) FROM login_attempts
+        WHERE ip_address = $1 AND NOT success
+          AND failure_reason IN ('invalid_password', 'invalid_two_factor_code', 'unknown_account')
+          AND created_at > NOW() - make_interval(secs => $2)
+        "#,
+    )
+    .bind(ip_address)
+    .bind(settings.lockout_ip_window_secs as f64)
+    .fetch_one(pool)
+    .await?;
+
+    if failures < settings.lockout_ip_max_failures {
+        return Ok(None);
+    }
+
+    // The oldest failure leaving the window brings the count back under the limit
+    let retry_at = oldest.unwrap_or_else(Utc::now)
+        + chrono::Duration::seconds(settings.lockout_ip_window_secs);
+    Ok(Some(seconds_until(retry_at)))
+}
+
+/// Count a failed sign-in against an account, locking it once the limit is reached.
+/// Returns the end of the lockout if this failure locked the account.
+pub async fn register_failure(
+    pool: &PgPool,
+    user_id: Uuid,
+    settings: &Settings,
+) -> Result<Option<DateTime<Utc>>> {
+    let mut tx = pool.begin().await?;
+
+    let (failed, lockouts): (i32, i32) = sqlx::query_as(
+        r#"
+        UPDATE users SET failed_login_count = failed_login_count + 1
+        WHERE id = $1
+        RETURNING failed_login_count, lockout_count
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if failed < settings.lockout_max_failures {
+        tx.commit().await?;
+        return Ok(None);
+    }
+
+    // Every further lockout doubles the duration, up to the configured maximum
+    let factor = 1i64
+        .checked_shl(lockouts.clamp(0, 32) as u32)
+        .unwrap_or(i64::MAX);
+    let duration = settings
+        .lockout_base_secs
+        .saturating_mul(factor)
+        .min(settings.lockout_max_secs);
+
+    let locked_until: DateTime<Utc> = sqlx::query_scalar(
+        r#"
+        UPDATE users SET
+            failed_login_count = 0,
+            lockout_count = lockout_count + 1,
+            locked_until = NOW() + make_interval(secs => $2)
+        WHERE id = $1
+        RETURNING locked_until
+        "#,
+    )
+    .bind(user_id)
+    .bind(duration as f64)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(locked_until))
+}
+
+/// Clear the failure count and lockout history after a successful sign-in
+pub async fn reset<'e>(executor: impl PgExecutor<'e>, user_id: Uuid) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE users SET failed_login_count = 0, lockout_count = 0, locked_until = NULL
+        WHERE id = $1 AND (failed_login_count > 0 OR lockout_count > 0 OR locked_until IS NOT NULL)
+        "#,
+    )
+    .bind(user_id)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Whole seconds from now until `at`, at least one
+pub fn seconds_until(at: DateTime<Utc>) -> u64 {
+    (at - Utc::now()).num_seconds().max(1) as u64
+}
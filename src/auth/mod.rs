@@ -1,9 +1,18 @@
 use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidateUrl, ValidationError};
 
+use crate::mentions;
+
+pub mod deletion;
 pub mod handler;
 pub mod jwt;
+pub mod lockout;
+pub mod oauth;
+pub mod roles;
+pub mod session;
+pub mod totp;
 pub mod utils;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -16,10 +25,42 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub email_verified: bool,
+    pub role: Role,
+    /// Set while the account is locked after repeated failed sign-ins
+    #[serde(skip_serializing)]
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+    /// Set once TOTP two-factor authentication has been confirmed
+    #[serde(skip_serializing)]
+    pub totp_enabled_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// Access level of a user; ordered so that a higher role includes the lower ones
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, Type, PartialEq, Eq, PartialOrd, Ord,
+)]
+#[sqlx(type_name = "user_role", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+/// Why a sign-in attempt failed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "login_failure_reason", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LoginFailure {
+    InvalidPassword,
+    UnknownAccount,
+    AccountLocked,
+    EmailNotVerified,
+    InvalidTwoFactorCode,
+}
+
 #[derive(Debug, sqlx::FromRow)]
 #[allow(dead_code)]
 pub struct AuthToken {
@@ -27,18 +68,38 @@ pub struct AuthToken {
     pub user_id: Uuid,
     pub token: String,
     pub token_type: String,
+    /// Address being switched to, for `email_change` tokens
+    pub new_email: Option<String>,
     pub expires_at: chrono::DateTime<chrono::Utc>,
     pub used_at: Option<chrono::DateTime<chrono::Utc>>,
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub refresh_token: String,
+    pub previous_refresh_token: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct RegisterUser {
-    #[validate(length(
-        min = 3,
-        max = 50,
-        message = "Username must be between 3 and 50 characters"
-    ))]
+    #[validate(
+        length(
+            min = 3,
+            max = 50,
+            message = "Username must be between 3 and 50 characters"
+        ),
+        custom(function = "validate_username")
+    )]
     pub username: String,
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
@@ -64,6 +125,18 @@ pub struct ForgotPasswordRequest {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct MagicLinkRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+/// Token from a magic sign-in link
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkVerifyRequest {
+    pub token: String,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ResetPasswordRequest {
     pub token: String,
@@ -71,18 +144,192 @@ pub struct ResetPasswordRequest {
     pub new_password: String,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub new_email: String,
+    /// Current password, to confirm it's really the account owner
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ConfirmEmailChangeRequest {
+    pub token: String,
+}
+
+/// Confirmation for deleting the current account
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+    /// Required when 2FA is enabled: a code from the authenticator app or a recovery code
+    pub code: Option<String>,
+}
+
+/// When a deleted account will actually be removed
+#[derive(Debug, Serialize)]
+pub struct AccountDeletionResponse {
+    pub deletion_scheduled_for: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct ResendVerificationRequest {
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
 }
 
+/// Request payload for updating the current user's profile.
+/// Omitted fields are left unchanged; an empty `bio` or `image` clears it.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfile {
+    #[validate(
+        length(
+            min = 3,
+            max = 50,
+            message = "Username must be between 3 and 50 characters"
+        ),
+        custom(function = "validate_username")
+    )]
+    pub username: Option<String>,
+    #[validate(length(max = 500, message = "Bio must be at most 500 characters"))]
+    pub bio: Option<String>,
+    #[validate(
+        length(max = 255, message = "Image URL must be at most 255 characters"),
+        custom(function = "validate_image_url")
+    )]
+    pub image: Option<String>,
+}
+
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    if mentions::is_mentionable(username) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("username").with_message(
+            "Username may only contain letters, digits, underscores, dots and hyphens, and must start and end with a letter, digit or underscore".into(),
+        ))
+    }
+}
+
+fn validate_image_url(image: &str) -> Result<(), ValidationError> {
+    if image.is_empty() || image.validate_url() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("url").with_message("Invalid image URL".into()))
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
+    pub refresh_token: String,
     pub user: UserResponse,
 }
 
+/// Returned by sign-in instead of tokens when the account has 2FA enabled
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub two_factor_required: bool,
+    /// Exchange this, together with a code, at `POST /api/auth/sign-in/2fa`
+    pub challenge_token: String,
+    pub expires_in: i64, // seconds
+}
+
+/// Second sign-in step for accounts with 2FA
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorVerifyRequest {
+    pub challenge_token: String,
+    /// A code from the authenticator app, or an unused recovery code
+    pub code: String,
+}
+
+/// A code from the authenticator app (or a recovery code, where accepted)
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+/// Secret to add to an authenticator app while enrolling in 2FA
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Freshly generated recovery codes; they are only ever shown once
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Where to send the user to sign in with (or link) an OAuth provider
+#[derive(Debug, Serialize)]
+pub struct OAuthAuthorizeResponse {
+    pub authorization_url: String,
+}
+
+/// Query parameters the provider redirected back with
+#[derive(Debug, Deserialize)]
+pub struct OAuthCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+/// An OAuth provider account linked to the user
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct IdentityResponse {
+    pub provider: String,
+    pub email: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+/// A signed-in device as shown to the user
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub id: Uuid,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub current: bool, // Whether this is the session making the request
+}
+
+/// Query parameters for the security log
+#[derive(Debug, Deserialize)]
+pub struct SecurityLogFilter {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+/// A sign-in attempt as shown in the user's security log
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct LoginAttemptResponse {
+    pub id: Uuid,
+    pub success: bool,
+    pub failure_reason: Option<LoginFailure>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserResponse {
     pub id: Uuid,
@@ -91,6 +338,8 @@ pub struct UserResponse {
     pub bio: Option<String>,
     pub image: Option<String>,
     pub email_verified: bool,
+    pub role: Role,
+    pub two_factor_enabled: bool,
 }
 
 impl From<User> for UserResponse {
@@ -102,6 +351,8 @@ impl From<User> for UserResponse {
             bio: user.bio,
             image: user.image,
             email_verified: user.email_verified,
+            role: user.role,
+            two_factor_enabled: user.totp_enabled_at.is_some(),
         }
     }
 }
@@ -0,0 +1,317 @@
+use anyhow::{bail, ensure, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    jwk::{Jwk, JwkSet},
+    DecodingKey, Validation,
+};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::PgConnection;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use url::Url;
+
+use crate::config::settings::OAuthProviderConfig;
+
+/// How long a user has to finish signing in at the provider
+pub const STATE_TTL_MINUTES: i64 = 10;
+/// Give up on a provider that takes longer than this to answer
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Largest provider response we are willing to read
+const MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+/// How long discovery documents and signing keys are reused before fetching them again
+const CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// What the provider told us about the account that signed in
+#[derive(Debug)]
+pub struct ProviderIdentity {
+    /// The provider's stable identifier for the account
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+}
+
+/// The parts of an OpenID Connect discovery document we use
+#[derive(Debug, Clone, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    email: Option<String>,
+    // Some providers send this as a string
+    email_verified: Option<Value>,
+    preferred_username: Option<String>,
+}
+
+struct Cached<T> {
+    value: T,
+    fetched_at: Instant,
+}
+
+/// Talks to OpenID Connect providers. Discovery documents (by issuer) and signing
+/// keys (by JWKS URL) are cached, so a sign-in doesn't fetch them every time.
+/// Cloning shares the HTTP connection pool and the caches.
+#[derive(Clone)]
+pub struct OAuthClient {
+    http: reqwest::Client,
+    discovery: Arc<Mutex<HashMap<String, Cached<Discovery>>>>,
+    jwks: Arc<Mutex<HashMap<String, Cached<JwkSet>>>>,
+}
+
+impl OAuthClient {
+    pub fn new() -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .user_agent("BlogVerse")
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            http,
+            discovery: Arc::new(Mutex::new(HashMap::new())),
+            jwks: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    /// Build the URL that sends the user to the provider to sign in. The code challenge
+    /// is the S256 PKCE transform of `code_verifier`, which stays on our side.
+    pub async fn authorization_url(
+        &self,
+        provider: &OAuthProviderConfig,
+        state: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<String> {
+        let discovery = self.discover(provider).await?;
+
+        let mut url = Url::parse(&discovery.authorization_endpoint)?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", &provider.redirect_uri)
+            .append_pair("scope", &provider.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", &pkce_challenge(code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url.into())
+    }
+
+    /// Exchange the authorization code for tokens and validate the ID token: signature
+    /// against the provider's published keys, issuer, audience, expiry and nonce
+    pub async fn authenticate(
+        &self,
+        provider: &OAuthProviderConfig,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<ProviderIdentity> {
+        let discovery = self.discover(provider).await?;
+
+        let response = self
+            .http
+            .post(&discovery.token_endpoint)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &provider.redirect_uri),
+                ("client_id", &provider.client_id),
+                ("client_secret", &provider.client_secret),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await?;
+        let tokens: TokenResponse = read_json(response).await?;
+
+        let header = jsonwebtoken::decode_header(&tokens.id_token)?;
+        let jwk = self
+            .signing_key(&discovery.jwks_uri, header.kid.as_deref())
+            .await?;
+
+        // The key type has to match the algorithm, so an HMAC-signed token can't pass
+        // for one signed with the provider's public key
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&provider.client_id]);
+        validation.set_issuer(&[&discovery.issuer]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(
+            &tokens.id_token,
+            &DecodingKey::from_jwk(&jwk)?,
+            &validation,
+        )?
+        .claims;
+
+        ensure!(
+            claims.nonce.as_deref() == Some(nonce),
+            "ID token nonce does not match"
+        );
+
+        Ok(ProviderIdentity {
+            subject: claims.sub,
+            email: claims.email,
+            email_verified: matches!(claims.email_verified, Some(Value::Bool(true)))
+                || matches!(claims.email_verified, Some(Value::String(ref s)) if s == "true"),
+            preferred_username: claims.preferred_username,
+        })
+    }
+
+    /// The provider's discovery document, which must be for the configured issuer
+    async fn discover(&self, provider: &OAuthProviderConfig) -> Result<Discovery> {
+        if let Some(discovery) = cached(&self.discovery, &provider.issuer) {
+            return Ok(discovery);
+        }
+
+        let discovery: Discovery = self
+            .get_json(&format!(
+                "{}/.well-known/openid-configuration",
+                provider.issuer.trim_end_matches('/')
+            ))
+            .await?;
+
+        ensure!(
+            discovery.issuer == provider.issuer,
+            "Discovery document is for issuer {}, expected {}",
+            discovery.issuer,
+            provider.issuer
+        );
+
+        store(&self.discovery, &provider.issuer, discovery.clone());
+        Ok(discovery)
+    }
+
+    /// The key an ID token was signed with. Keys we haven't seen are looked up again
+    /// at the provider, as they may have rotated them since we cached the set.
+    async fn signing_key(&self, jwks_uri: &str, kid: Option<&str>) -> Result<Jwk> {
+        let find = |jwks: &JwkSet| {
+            match kid {
+                Some(kid) => jwks.find(kid),
+                None => jwks.keys.first(),
+            }
+            .cloned()
+        };
+
+        if let Some(jwk) = cached(&self.jwks, jwks_uri).and_then(|jwks| find(&jwks)) {
+            return Ok(jwk);
+        }
+
+        let jwks: JwkSet = self.get_json(jwks_uri).await?;
+        let jwk = find(&jwks);
+        store(&self.jwks, jwks_uri, jwks);
+
+        jwk.context("No matching signing key for the ID token")
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let response = self
+            .http
+            .get(url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .send()
+            .await?;
+        read_json(response).await
+    }
+}
+
+/// Pick a free username for a new account, based on the provider's preferred username
+/// or the local part of the email
+pub async fn available_username(conn: &mut PgConnection, source: &str) -> Result<String> {
+    let mut base: String = source
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        .take(40)
+        .collect::<String>()
+        .trim_matches(|c| c == '.' || c == '-')
+        .to_string();
+    if base.len() < 3 {
+        base = format!("user{}", base);
+    }
+
+    for attempt in 0..10 {
+        let candidate = if attempt == 0 {
+            base.clone()
+        } else {
+            format!("{}{}", base, rand::thread_rng().gen_range(1000..10000))
+        };
+
+        let taken: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)")
+                .bind(&candidate)
+                .fetch_one(&mut *conn)
+                .await?;
+
+        if !taken {
+            return Ok(candidate);
+        }
+    }
+
+    bail!("No free username found for {}", base)
+}
+
+/// PKCE S256 code challenge (RFC 7636)
+fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+/// Read a successful JSON response, refusing bodies larger than we ever expect
+async fn read_json<T: DeserializeOwned>(mut response: reqwest::Response) -> Result<T> {
+    let status = response.status();
+    let url = response.url().clone();
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        ensure!(
+            body.len() + chunk.len() <= MAX_RESPONSE_BYTES,
+            "Response from {} is too large",
+            url
+        );
+        body.extend_from_slice(&chunk);
+    }
+
+    ensure!(
+        status.is_success(),
+        "{} returned {}: {}",
+        url,
+        status,
+        String::from_utf8_lossy(&body)
+    );
+    Ok(serde_json::from_slice(&body)?)
+}
+
+fn cached<T: Clone>(cache: &Mutex<HashMap<String, Cached<T>>>, key: &str) -> Option<T> {
+    let cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(key)
+        .filter(|entry| entry.fetched_at.elapsed() < CACHE_TTL)
+        .map(|entry| entry.value.clone())
+}
+
+fn store<T>(cache: &Mutex<HashMap<String, Cached<T>>>, key: &str, value: T) {
+    let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
+    cache.insert(
+        key.to_string(),
+        Cached {
+            value,
+            fetched_at: Instant::now(),
+        },
+    );
+}
@@ -0,0 +1,62 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, StatusCode},
+};
+use sqlx::PgPool;
+use std::marker::PhantomData;
+
+use crate::{
+    auth::{jwt::Claims, Role},
+    config::settings::Settings,
+};
+
+/// Minimum role a `RequireRole` extractor accepts
+pub trait RoleRequirement {
+    const MIN_ROLE: Role;
+}
+
+/// Moderators and admins
+pub struct Moderator;
+
+impl RoleRequirement for Moderator {
+    const MIN_ROLE: Role = Role::Moderator;
+}
+
+/// Admins only
+pub struct Admin;
+
+impl RoleRequirement for Admin {
+    const MIN_ROLE: Role = Role::Admin;
+}
+
+/// Extractor for authenticated users with at least role `R`,
+/// e.g. `RequireRole<Moderator>`. Responds 403 for users with a lower role.
+pub struct RequireRole<R: RoleRequirement> {
+    pub claims: Claims,
+    _role: PhantomData<R>,
+}
+
+#[async_trait]
+impl<S, R> FromRequestParts<S> for RequireRole<R>
+where
+    S: Send + Sync,
+    Settings: FromRef<S>,
+    PgPool: FromRef<S>,
+    R: RoleRequirement,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = Claims::from_request_parts(parts, state).await?;
+
+        if claims.role < R::MIN_ROLE {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(RequireRole {
+            claims,
+            _role: PhantomData,
+        })
+    }
+}
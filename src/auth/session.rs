@@ -0,0 +1,66 @@
+use anyhow::Result;
+use axum::http::{header::USER_AGENT, HeaderMap};
+use chrono::{Duration, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::auth::{jwt, utils, Role, Session};
+
+/// How long a refresh token stays valid after it was issued or last rotated
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Start a new session for a user and return an access token + refresh token pair
+pub async fn start(
+    pool: &PgPool,
+    user_id: Uuid,
+    role: Role,
+    jwt_secret: &str,
+    headers: &HeaderMap,
+    ip_address: Option<String>,
+) -> Result<(String, String)> {
+    let refresh_token = utils::generate_secure_token();
+    let expires_at = Utc::now() + Duration::days(REFRESH_TOKEN_TTL_DAYS);
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+
+    let session = sqlx::query_as::<_, Session>(
+        r#"
+        INSERT INTO sessions (user_id, refresh_token, user_agent, ip_address, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(&refresh_token)
+    .bind(user_agent)
+    .bind(ip_address)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    let token = jwt::create_token(user_id, session.id, role, jwt_secret)?;
+
+    Ok((token, refresh_token))
+}
+
+/// Revoke every active session of a user, optionally keeping one (e.g. the current one)
+pub async fn revoke_all<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+    except: Option<Uuid>,
+) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        UPDATE sessions SET revoked_at = NOW()
+        WHERE user_id = $1 AND revoked_at IS NULL AND ($2::UUID IS NULL OR id != $2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(except)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,305 @@
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::{Rng, RngCore};
+use sha1::Sha1;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::auth::utils;
+
+/// Name shown next to the account in authenticator apps
+const ISSUER: &str = "BlogVerse";
+/// Seconds per TOTP time step (RFC 6238 default)
+const STEP_SECS: i64 = 30;
+const DIGITS: u32 = 6;
+/// Accept codes from one step before or after the current one to allow for clock drift
+const ALLOWED_DRIFT: i64 = 1;
+/// How long the challenge token from the first sign-in step stays valid
+pub const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// How many recovery codes a user gets when enabling 2FA
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Generate a random 160-bit secret, base32 encoded
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32_encode(&bytes)
+}
+
+/// `otpauth://` URI for enrolling the secret in an authenticator app
+pub fn otpauth_uri(account: &str, secret: &str) -> String {
+    let label = format!("{}:{}", ISSUER, account);
+    format!(
+        "otpauth://totp/{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        utf8_percent_encode(&label, NON_ALPHANUMERIC),
+        secret,
+        utf8_percent_encode(ISSUER, NON_ALPHANUMERIC),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+/// Check a code against the secret and return the time step it matched.
+/// Steps at or before `last_step` are rejected so a code can only be used once.
+pub fn verify_code(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let key = base32_decode(secret)?;
+    let current = Utc::now().timestamp() / STEP_SECS;
+
+    (current - ALLOWED_DRIFT..=current + ALLOWED_DRIFT)
+        .filter(|step| last_step.is_none_or(|last| *step > last))
+        .find(|step| hotp(&key, *step as u64) == code)
+}
+
+/// Generate a fresh set of recovery codes, formatted like `a1b2c-d3e4f`
+pub fn generate_recovery_codes() -> Vec<String> {
+    const CHARSET: &[u8] = b"abcdefghijkmnpqrstuvwxyz23456789";
+
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| CHARSET[rng.gen_range(0..CHARSET.len())] as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// Replace a user's recovery codes with new ones and return them in plain text.
+/// Only the hashes are stored, so this is the only time the user sees them.
+pub async fn replace_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<Vec<String>> {
+    sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let codes = generate_recovery_codes();
+    for code in &codes {
+        sqlx::query("INSERT INTO recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(utils::hash_password(code)?)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(codes)
+}
+
+/// Verify a second factor for a user with 2FA enabled: either a TOTP code or an unused
+/// recovery code. Marks whatever was used so it can't be used again.
+pub async fn verify_second_factor(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    code: &str,
+) -> Result<bool> {
+    // Lock the row so two requests can't accept the same code
+    let (secret, last_step): (Option<String>, Option<i64>) = sqlx::query_as(
+        "SELECT totp_secret, totp_last_step FROM users WHERE id = $1 AND totp_enabled_at IS NOT NULL FOR UPDATE",
+    )
+    .bind(user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .unwrap_or((None, None));
+
+    let Some(secret) = secret else {
+        return Ok(false);
+    };
+
+    if let Some(step) = verify_code(&secret, code, last_step) {
+        sqlx::query("UPDATE users SET totp_last_step = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(step)
+            .execute(&mut *conn)
+            .await?;
+        return Ok(true);
+    }
+
+    // Recovery codes are compared against each unused hash
+    let normalized = code.trim().to_lowercase();
+    let unused: Vec<(Uuid, String)> = sqlx::query_as(
+        "SELECT id, code_hash FROM recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for (id, hash) in unused {
+        if utils::verify_password(&hash, &normalized).is_ok() {
+            sqlx::query("UPDATE recovery_codes SET used_at = NOW() WHERE id = $1")
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// HOTP value (RFC 4226) for a counter, zero-padded to `DIGITS`
+fn hotp(key: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// RFC 4648 base32 without padding, as authenticator apps expect
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    out
+}
+
+fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0u32);
+
+    for c in input.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shared secret of the RFC 4226 and RFC 6238 test vectors
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn hotp_matches_rfc4226_vectors() {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(
+                hotp(RFC_SECRET, counter as u64),
+                *code,
+                "counter {}",
+                counter
+            );
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc6238_sha1_vectors() {
+        // The RFC lists 8-digit codes; ours are their last 6 digits
+        let expected = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        for (time, code) in expected {
+            assert_eq!(
+                hotp(RFC_SECRET, (time / STEP_SECS) as u64),
+                code,
+                "time {}",
+                time
+            );
+        }
+    }
+
+    #[test]
+    fn base32_matches_rfc4648_vectors() {
+        let expected = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+        for (plain, encoded) in expected {
+            assert_eq!(base32_encode(plain.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(
+            base32_encode(RFC_SECRET),
+            "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+        );
+    }
+
+    #[test]
+    fn base32_round_trips() {
+        for len in 0..=40 {
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            assert_eq!(base32_decode(&base32_encode(&bytes)).unwrap(), bytes);
+        }
+
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(base32_decode(&secret).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn base32_decode_accepts_padding_and_lowercase() {
+        assert_eq!(base32_decode("mzxw6ytboi======").unwrap(), b"foobar");
+        assert_eq!(base32_decode("MZXW1"), None);
+    }
+
+    #[test]
+    fn verify_code_accepts_current_code_once() {
+        let secret = generate_secret();
+        let key = base32_decode(&secret).unwrap();
+        let step = Utc::now().timestamp() / STEP_SECS;
+        let code = hotp(&key, step as u64);
+
+        let matched = verify_code(&secret, &code, None).expect("current code is accepted");
+        assert!((step - ALLOWED_DRIFT..=step + ALLOWED_DRIFT).contains(&matched));
+        assert_eq!(verify_code(&secret, &code, Some(matched)), None);
+        assert_eq!(verify_code(&secret, "12345", None), None);
+        assert_eq!(verify_code(&secret, "12a456", None), None);
+    }
+}
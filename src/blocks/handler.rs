@@ -0,0 +1,214 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::jwt,
+    blocks::{BlockActionResponse, BlockListFilter, BlockedUserResponse, MuteActionResponse},
+    error::AppError,
+    response::{ApiResponse, Cursor, Pagination},
+};
+
+async fn ensure_other_user(pool: &PgPool, current_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+    if current_id == user_id {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot block or mute yourself".to_string(),
+        ));
+    }
+
+    sqlx::query("SELECT id FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    Ok(())
+}
+
+/// Block a user, removing any follows between the two of you
+/// POST /api/user/:id/block
+pub async fn block_user(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_other_user(&pool, claims.sub, user_id).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_blocks (blocker_id, blocked_id)
+        VALUES ($1, $2)
+        ON CONFLICT (blocker_id, blocked_id) DO NOTHING
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    // Unfollow in both directions; the follow count trigger adjusts both users' counts
+    sqlx::query(
+        r#"
+        DELETE FROM follows
+        WHERE (follower_id = $1 AND following_id = $2) OR (follower_id = $2 AND following_id = $1)
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(BlockActionResponse { blocked: true }))
+}
+
+/// Unblock a user
+/// DELETE /api/user/:id/block
+pub async fn unblock_user(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2")
+        .bind(claims.sub)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(BlockActionResponse { blocked: false }))
+}
+
+/// Mute a user, hiding their stories from your feeds
+/// POST /api/user/:id/mute
+pub async fn mute_user(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_other_user(&pool, claims.sub, user_id).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO user_mutes (muter_id, muted_id)
+        VALUES ($1, $2)
+        ON CONFLICT (muter_id, muted_id) DO NOTHING
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(user_id)
+    .execute(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(MuteActionResponse { muted: true }))
+}
+
+/// Unmute a user
+/// DELETE /api/user/:id/mute
+pub async fn unmute_user(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(user_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("DELETE FROM user_mutes WHERE muter_id = $1 AND muted_id = $2")
+        .bind(claims.sub)
+        .bind(user_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(MuteActionResponse { muted: false }))
+}
+
+/// Get the users you blocked, most recent first
+/// GET /api/user/me/blocks
+pub async fn get_blocked_users(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Query(filter): Query<BlockListFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+
+    // Fetch one extra row to know whether there is a next page
+    let mut users = sqlx::query_as::<_, BlockedUserResponse>(
+        r#"
+        SELECT u.id, u.username, u.bio, u.image, b.created_at as since
+        FROM user_blocks b
+        JOIN users u ON b.blocked_id = u.id
+        WHERE b.blocker_id = $1
+          AND ($3::TIMESTAMPTZ IS NULL OR (b.created_at, u.id) < ($3, $4))
+        ORDER BY b.created_at DESC, u.id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(limit + 1)
+    .bind(cursor.as_ref().map(|c| c.timestamp))
+    .bind(cursor.as_ref().map(|c| c.id))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    let pagination = Pagination::from_rows(&mut users, limit, |u| Cursor::new(u.since, u.id));
+
+    Ok(ApiResponse::paginated(users, pagination))
+}
+
+/// Get the users you muted, most recent first
+/// GET /api/user/me/mutes
+pub async fn get_muted_users(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Query(filter): Query<BlockListFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+
+    // Fetch one extra row to know whether there is a next page
+    let mut users = sqlx::query_as::<_, BlockedUserResponse>(
+        r#"
+        SELECT u.id, u.username, u.bio, u.image, m.created_at as since
+        FROM user_mutes m
+        JOIN users u ON m.muted_id = u.id
+        WHERE m.muter_id = $1
+          AND ($3::TIMESTAMPTZ IS NULL OR (m.created_at, u.id) < ($3, $4))
+        ORDER BY m.created_at DESC, u.id DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(limit + 1)
+    .bind(cursor.as_ref().map(|c| c.timestamp))
+    .bind(cursor.as_ref().map(|c| c.id))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    let pagination = Pagination::from_rows(&mut users, limit, |u| Cursor::new(u.since, u.id));
+
+    Ok(ApiResponse::paginated(users, pagination))
+}
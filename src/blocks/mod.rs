@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub mod handler;
+
+/// Database model for a block
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct Block {
+    pub blocker_id: Uuid,
+    pub blocked_id: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Database model for a mute
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct Mute {
+    pub muter_id: Uuid,
+    pub muted_id: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Query parameters for the blocked/muted users lists
+#[derive(Debug, Deserialize)]
+pub struct BlockListFilter {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+/// A user in the blocked or muted users list
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BlockedUserResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub since: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for block/unblock actions
+#[derive(Debug, Serialize)]
+pub struct BlockActionResponse {
+    pub blocked: bool,
+}
+
+/// Response for mute/unmute actions
+#[derive(Debug, Serialize)]
+pub struct MuteActionResponse {
+    pub muted: bool,
+}
+
+/// Whether either user has blocked the other.
+/// Blocks apply in both directions: neither side can follow, comment on or clap for the other.
+pub async fn is_blocked<'e>(
+    executor: impl PgExecutor<'e>,
+    user_a: Uuid,
+    user_b: Uuid,
+) -> Result<bool, AppError> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM user_blocks
+            WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1)
+        )
+        "#,
+    )
+    .bind(user_a)
+    .bind(user_b)
+    .fetch_one(executor)
+    .await
+    .map_err(|_| AppError::InternalServerError)
+}
@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use sqlx::{FromRow, PgPool, Row};
+use uuid::Uuid;
+
+use crate::{
+    auth::jwt,
+    bookmarks::{BookmarkActionResponse, BookmarkListFilter, BookmarkListResponse},
+    error::AppError,
+    mentions::MentionSpan,
+    response::ApiResponse,
+    stories::{AuthorResponse, StoryResponse, StoryStatus},
+};
+
+/// Bookmark a story
+/// POST /api/stories/:id/bookmark
+pub async fn add_bookmark(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(story_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    // Verify story exists and is readable by the user
+    sqlx::query(
+        "SELECT id FROM stories WHERE id = $1 AND ((status = 'published' AND hidden_at IS NULL) OR author_id = $2)",
+    )
+    .bind(story_id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Story not found".to_string()))?;
+
+    // Insert bookmark (ignore if already bookmarked)
+    sqlx::query(
+        r#"
+        INSERT INTO bookmarks (user_id, story_id)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, story_id) DO NOTHING
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(story_id)
+    .execute(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(BookmarkActionResponse {
+        bookmarked: true,
+    }))
+}
+
+/// Remove a story from bookmarks
+/// DELETE /api/stories/:id/bookmark
+pub async fn remove_bookmark(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(story_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("DELETE FROM bookmarks WHERE user_id = $1 AND story_id = $2")
+        .bind(claims.sub)
+        .bind(story_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(BookmarkActionResponse {
+        bookmarked: false,
+    }))
+}
+
+/// Helper struct for fetching bookmarked stories with author info
+#[derive(FromRow)]
+struct StoryFromDb {
+    id: Uuid,
+    title: String,
+    subtitle: Option<String>,
+    content: serde_json::Value,
+    slug: String,
+    status: StoryStatus,
+    clap_count: i32,
+    created_at: chrono::DateTime<chrono::Utc>,
+    published_at: Option<chrono::DateTime<chrono::Utc>>,
+    scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
+    author_id: Uuid,
+    username: String,
+    bio: Option<String>,
+    image: Option<String>,
+    tags: Vec<String>,
+    mentions: sqlx::types::Json<Vec<MentionSpan>>,
+}
+
+impl From<StoryFromDb> for StoryResponse {
+    fn from(s: StoryFromDb) -> Self {
+        StoryResponse {
+            id: s.id,
+            author: AuthorResponse {
+                id: s.author_id,
+                username: s.username,
+                bio: s.bio,
+                image: s.image,
+            },
+            title: s.title,
+            subtitle: s.subtitle,
+            content: s.content,
+            slug: s.slug,
+            status: s.status,
+            clap_count: s.clap_count,
+            tags: s.tags,
+            mentions: s.mentions.0,
+            is_bookmarked: true,
+            created_at: s.created_at,
+            published_at: s.published_at,
+            scheduled_for: s.scheduled_for,
+        }
+    }
+}
+
+/// Get the current user's reading list (most recently bookmarked first)
+/// GET /api/user/me/bookmarks
+pub async fn get_bookmarks(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Query(filter): Query<BookmarkListFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let offset = filter.offset.unwrap_or(0).max(0);
+
+    // Stories that were unpublished after being bookmarked are hidden unless they are your own
+    let total_row = sqlx::query(
+        r#"
+        SELECT COUNT(*) as count
+        FROM bookmarks b
+        JOIN stories s ON b.story_id = s.id
+        WHERE b.user_id = $1 AND ((s.status = 'published' AND s.hidden_at IS NULL) OR s.author_id = $1)
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    let total: i64 = total_row.get("count");
+
+    let stories = sqlx::query_as::<_, StoryFromDb>(
+        r#"
+        SELECT
+            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count,
+            s.created_at, s.published_at, s.scheduled_for, s.author_id,
+            u.username, u.bio, u.image,
+            story_mentions(s.id) as mentions,
+            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{}') as tags
+        FROM bookmarks b
+        JOIN stories s ON b.story_id = s.id
+        JOIN users u ON s.author_id = u.id
+        LEFT JOIN story_tags st ON s.id = st.story_id
+        LEFT JOIN tags t ON st.tag_id = t.id
+        WHERE b.user_id = $1 AND ((s.status = 'published' AND s.hidden_at IS NULL) OR s.author_id = $1)
+        GROUP BY s.id, u.id, b.created_at
+        ORDER BY b.created_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Bookmarks error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let stories: Vec<StoryResponse> = stories.into_iter().map(StoryResponse::from).collect();
+    let has_more = (offset + limit) < total;
+
+    Ok(ApiResponse::success(BookmarkListResponse {
+        stories,
+        total,
+        has_more,
+    }))
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::stories::StoryResponse;
+
+pub mod handler;
+
+/// Database model for a bookmarked story
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct Bookmark {
+    pub user_id: Uuid,
+    pub story_id: Uuid,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Query parameters for the paginated reading list
+#[derive(Debug, Deserialize)]
+pub struct BookmarkListFilter {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Response for the paginated reading list
+#[derive(Debug, Serialize)]
+pub struct BookmarkListResponse {
+    pub stories: Vec<StoryResponse>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+/// Response for bookmark/unbookmark actions
+#[derive(Debug, Serialize)]
+pub struct BookmarkActionResponse {
+    pub bookmarked: bool,
+}
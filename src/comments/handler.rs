@@ -3,18 +3,29 @@ use axum::{
     response::IntoResponse,
     Json,
 };
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{FromRow, PgPool, Row};
+use std::collections::HashMap;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::jwt,
+    auth::{jwt, Role},
+    blocks,
     comments::{
-        CommentAuthor, CommentFilter, CommentResponse, CommentWithReplies, CommentsListResponse,
-        CreateComment, UpdateComment,
+        CommentAuthor, CommentDeletion, CommentFilter, CommentNode, CommentPolicy, CommentResponse,
+        CommentSettings, CommentTreeFilter, CommentTreeResponse, CommentVersion,
+        CommentWithReplies, CommentsListResponse, CreateComment, HideCommentRequest, UpdateComment,
+        UpdateCommentSettings,
     },
+    config::settings::Settings,
+    email::EmailService,
     error::AppError,
-    response::ApiResponse,
+    follows,
+    mentions::{self, MentionSpan, MentionTarget},
+    moderation::{self, ModerationAction, ModerationReason, VisibilityResponse},
+    notifications::{self, NotificationType},
+    response::{ApiResponse, Cursor, Pagination},
 };
 
 /// Helper struct for fetching comments with author info from database
@@ -22,43 +33,129 @@ use crate::{
 struct CommentFromDb {
     id: Uuid,
     story_id: Uuid,
-    author_id: Uuid,
+    author_id: Option<Uuid>, // Null once a tombstone's author deletes their account
     parent_id: Option<Uuid>,
     content: String,
     clap_count: i32,
     created_at: chrono::DateTime<chrono::Utc>,
     updated_at: chrono::DateTime<chrono::Utc>,
+    edited_at: Option<chrono::DateTime<chrono::Utc>>,
+    deleted_by: Option<CommentDeletion>,
+    is_pinned: bool,
+    mentions: sqlx::types::Json<Vec<MentionSpan>>,
     // Author fields
-    username: String,
+    username: Option<String>,
     image: Option<String>,
     // Replies count
     replies_count: i64,
 }
 
+/// A comment in a tree, with how deep it sits (top level being 1)
+#[derive(FromRow)]
+struct CommentTreeRow {
+    #[sqlx(flatten)]
+    comment: CommentFromDb,
+    depth: i32,
+}
+
 impl From<CommentFromDb> for CommentResponse {
     fn from(c: CommentFromDb) -> Self {
+        // Tombstones keep their place in the thread but not their author or content
+        let (author, content, mentions) = match (c.deleted_by, c.author_id, c.username) {
+            (None, Some(id), Some(username)) => (
+                Some(CommentAuthor {
+                    id,
+                    username,
+                    image: c.image,
+                }),
+                c.content,
+                c.mentions.0,
+            ),
+            (deleted_by, _, _) => (
+                None,
+                deleted_by
+                    .unwrap_or(CommentDeletion::Author)
+                    .placeholder()
+                    .to_string(),
+                Vec::new(),
+            ),
+        };
+
         CommentResponse {
             id: c.id,
             story_id: c.story_id,
-            author: CommentAuthor {
-                id: c.author_id,
-                username: c.username,
-                image: c.image,
-            },
+            author,
             parent_id: c.parent_id,
-            content: c.content,
+            content,
+            mentions,
             clap_count: c.clap_count,
             replies_count: c.replies_count,
+            is_deleted: c.deleted_by.is_some(),
+            is_pinned: c.is_pinned,
+            is_edited: c.edited_at.is_some(),
+            edited_at: c.edited_at,
             created_at: c.created_at,
             updated_at: c.updated_at,
         }
     }
 }
 
+/// ORDER BY and keyset clauses for a comment sort mode.
+/// Keyset clause parameters start at $4 (clap count first when sorting by claps).
+fn comment_sort_clauses(sort: Option<&str>) -> (&'static str, &'static str) {
+    match sort {
+        Some("oldest") => (
+            "c.created_at ASC, c.id ASC",
+            "AND (c.created_at, c.id) > ($4, $5)",
+        ),
+        Some("claps") => (
+            "c.clap_count DESC, c.created_at DESC, c.id DESC",
+            "AND (c.clap_count, c.created_at, c.id) < ($4, $5, $6)",
+        ),
+        _ => (
+            "c.created_at DESC, c.id DESC", // Default: latest
+            "AND (c.created_at, c.id) < ($4, $5)",
+        ),
+    }
+}
+
+fn comment_cursor(c: &CommentFromDb, by_claps: bool) -> Cursor {
+    if by_claps {
+        Cursor::with_claps(c.clap_count, c.created_at, c.id)
+    } else {
+        Cursor::new(c.created_at, c.id)
+    }
+}
+
+/// Attach a comment's loaded replies (recursively) and note where they were cut off
+fn build_comment_node(
+    comment: CommentFromDb,
+    replies: &mut HashMap<Uuid, Vec<CommentFromDb>>,
+    by_claps: bool,
+) -> CommentNode {
+    let loaded = replies.remove(&comment.id).unwrap_or_default();
+    let has_more_replies = (loaded.len() as i64) < comment.replies_count;
+    let replies_cursor = loaded
+        .last()
+        .filter(|_| has_more_replies)
+        .map(|last| comment_cursor(last, by_claps).encode());
+
+    CommentNode {
+        comment: CommentResponse::from(comment),
+        replies: loaded
+            .into_iter()
+            .map(|reply| build_comment_node(reply, replies, by_claps))
+            .collect(),
+        has_more_replies,
+        replies_cursor,
+    }
+}
+
 /// Create a new comment on a story
 /// POST /api/stories/:id/comments
 pub async fn create_comment(
     State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
     claims: jwt::Claims,
     Path(story_id): Path<Uuid>,
     Json(payload): Json<CreateComment>,
@@ -68,21 +165,46 @@ pub async fn create_comment(
         .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
 
     // Verify story exists and is published
-    sqlx::query("SELECT id FROM stories WHERE id = $1 AND status = 'published'")
+    let story = sqlx::query("SELECT author_id, comment_policy FROM stories WHERE id = $1 AND status = 'published' AND hidden_at IS NULL")
         .bind(story_id)
         .fetch_optional(&pool)
         .await
         .map_err(|_| AppError::InternalServerError)?
         .ok_or(AppError::NotFound("Story not found".to_string()))?;
 
+    let story_author_id: Uuid = story.get("author_id");
+    let policy: CommentPolicy = story.get("comment_policy");
+
+    // The story's author decides who may respond
+    match policy {
+        CommentPolicy::Everyone => {}
+        CommentPolicy::Followers => {
+            if claims.sub != story_author_id
+                && !follows::is_following(&pool, claims.sub, story_author_id).await?
+            {
+                return Err(AppError::UnprocessableEntity(
+                    "Only followers of the author can comment on this story".to_string(),
+                ));
+            }
+        }
+        CommentPolicy::Disabled => {
+            return Err(AppError::UnprocessableEntity(
+                "Comments are turned off for this story".to_string(),
+            ));
+        }
+    }
+
     // If replying to a comment, verify parent exists and belongs to same story
+    let mut parent_author_id: Option<Uuid> = None;
     if let Some(parent_id) = payload.parent_id {
-        let parent = sqlx::query("SELECT story_id FROM comments WHERE id = $1")
-            .bind(parent_id)
-            .fetch_optional(&pool)
-            .await
-            .map_err(|_| AppError::InternalServerError)?
-            .ok_or(AppError::NotFound("Parent comment not found".to_string()))?;
+        let parent = sqlx::query(
+            "SELECT story_id, author_id FROM comments WHERE id = $1 AND hidden_at IS NULL AND deleted_at IS NULL",
+        )
+        .bind(parent_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("Parent comment not found".to_string()))?;
 
         let parent_story_id: Uuid = parent.get("story_id");
         if parent_story_id != story_id {
@@ -90,10 +212,26 @@ pub async fn create_comment(
                 "Parent comment does not belong to this story".to_string(),
             ));
         }
+
+        parent_author_id = Some(parent.get("author_id"));
+    }
+
+    // Blocked users can't comment on each other's stories or reply to each other
+    for owner_id in std::iter::once(story_author_id).chain(parent_author_id) {
+        if blocks::is_blocked(&pool, owner_id, claims.sub).await? {
+            return Err(AppError::UnprocessableEntity(
+                "You cannot comment here".to_string(),
+            ));
+        }
     }
 
     let now = chrono::Utc::now();
 
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
     let comment = sqlx::query_as::<_, crate::comments::Comment>(
         r#"
         INSERT INTO comments (story_id, author_id, parent_id, content, created_at, updated_at)
@@ -107,44 +245,76 @@ pub async fn create_comment(
     .bind(&payload.content)
     .bind(now)
     .bind(now)
-    .fetch_one(&pool)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| {
         tracing::error!("Failed to create comment: {:?}", e);
         AppError::InternalServerError
     })?;
 
+    mentions::sync_comment(&mut tx, comment.id, &comment.content).await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Replies notify the parent comment's author, top-level comments the story's author
+    let (recipient_id, notification_type) = match parent_author_id {
+        Some(parent_author_id) => (parent_author_id, NotificationType::Reply),
+        None => (story_author_id, NotificationType::Comment),
+    };
+    notifications::notify(
+        &pool,
+        recipient_id,
+        claims.sub,
+        notification_type,
+        Some(story_id),
+        Some(comment.id),
+    )
+    .await;
+    mentions::notify(&pool, &email_service, MentionTarget::Comment(comment.id)).await;
+
     // Fetch the complete comment with author info
     get_comment_response(&pool, comment.id).await
 }
 
-/// Get all top-level comments for a story (with replies count)
+/// Get all top-level comments for a story (with replies count).
+/// The comment pinned by the story's author comes first, on the first page.
 /// GET /api/stories/:id/comments
 pub async fn get_story_comments(
     State(pool): State<PgPool>,
     Path(story_id): Path<Uuid>,
     Query(filter): Query<CommentFilter>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Verify story exists
-    sqlx::query("SELECT id FROM stories WHERE id = $1")
+    // Verify story exists and hasn't been hidden by moderation
+    sqlx::query("SELECT id FROM stories WHERE id = $1 AND hidden_at IS NULL")
         .bind(story_id)
         .fetch_optional(&pool)
         .await
         .map_err(|_| AppError::InternalServerError)?
         .ok_or(AppError::NotFound("Story not found".to_string()))?;
 
-    let limit = filter.limit.unwrap_or(20).min(100);
-    let offset = filter.offset.unwrap_or(0);
-
-    let order_clause = match filter.sort.as_deref() {
-        Some("oldest") => "c.created_at ASC",
-        Some("claps") => "c.clap_count DESC, c.created_at DESC",
-        _ => "c.created_at DESC", // Default: latest
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let sort = filter.sort.as_deref();
+    let by_claps = sort == Some("claps");
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, by_claps))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        filter.offset.unwrap_or(0)
     };
 
+    let (order_clause, keyset_clause) = comment_sort_clauses(sort);
+    let keyset_clause = if cursor.is_some() { keyset_clause } else { "" };
+
     // Get total count of top-level comments
     let total_row = sqlx::query(
-        "SELECT COUNT(*) as count FROM comments WHERE story_id = $1 AND parent_id IS NULL",
+        "SELECT COUNT(*) as count FROM comments WHERE story_id = $1 AND parent_id IS NULL AND hidden_at IS NULL",
     )
     .bind(story_id)
     .fetch_one(&pool)
@@ -158,39 +328,205 @@ pub async fn get_story_comments(
         r#"
         SELECT 
             c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count, 
-            c.created_at, c.updated_at,
+            c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+            COALESCE(c.id = (SELECT pinned_comment_id FROM stories WHERE id = c.story_id) AND c.deleted_at IS NULL, FALSE) as is_pinned,
+            comment_mentions(c.id) as mentions,
             u.username, u.image,
-            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id) as replies_count
+            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count
         FROM comments c
-        JOIN users u ON c.author_id = u.id
-        WHERE c.story_id = $1 AND c.parent_id IS NULL
+        LEFT JOIN users u ON c.author_id = u.id
+        WHERE c.story_id = $1 AND c.parent_id IS NULL AND c.hidden_at IS NULL
+            AND c.id IS DISTINCT FROM (SELECT pinned_comment_id FROM stories WHERE id = $1) {}
         ORDER BY {}
         LIMIT $2 OFFSET $3
         "#,
-        order_clause
+        keyset_clause, order_clause
     );
 
-    let comments = sqlx::query_as::<_, CommentFromDb>(&query_str)
+    // Fetch one extra row to know whether there is a next page
+    let mut query = sqlx::query_as::<_, CommentFromDb>(&query_str)
+        .bind(story_id)
+        .bind(limit + 1)
+        .bind(offset);
+    if let Some(cursor) = cursor {
+        if by_claps {
+            query = query.bind(cursor.clap_count);
+        }
+        query = query.bind(cursor.timestamp).bind(cursor.id);
+    }
+
+    let mut comments = query.fetch_all(&pool).await.map_err(|e| {
+        tracing::error!("Failed to fetch comments: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let pagination = Pagination::from_rows(&mut comments, limit, |c| comment_cursor(c, by_claps));
+
+    // The pinned comment is kept out of the pages above and shown ahead of the first one
+    let pinned = if cursor.is_none() && offset == 0 {
+        sqlx::query_as::<_, CommentFromDb>(
+            r#"
+            SELECT
+                c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count,
+                c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+                TRUE as is_pinned,
+                comment_mentions(c.id) as mentions,
+                u.username, u.image,
+                (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count
+            FROM stories s
+            JOIN comments c ON c.id = s.pinned_comment_id
+            LEFT JOIN users u ON c.author_id = u.id
+            WHERE s.id = $1 AND c.hidden_at IS NULL AND c.deleted_at IS NULL
+            "#,
+        )
         .bind(story_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&pool)
+        .fetch_optional(&pool)
         .await
-        .map_err(|e| {
-            tracing::error!("Failed to fetch comments: {:?}", e);
-            AppError::InternalServerError
-        })?;
+        .map_err(|_| AppError::InternalServerError)?
+    } else {
+        None
+    };
 
-    let comments_response: Vec<CommentResponse> =
-        comments.into_iter().map(CommentResponse::from).collect();
+    let comments_response: Vec<CommentResponse> = pinned
+        .into_iter()
+        .chain(comments)
+        .map(CommentResponse::from)
+        .collect();
 
-    let has_more = (offset + limit) < total;
+    Ok(ApiResponse::paginated(
+        CommentsListResponse {
+            comments: comments_response,
+            total,
+        },
+        pagination,
+    ))
+}
 
-    Ok(ApiResponse::success(CommentsListResponse {
-        comments: comments_response,
-        total,
-        has_more,
-    }))
+/// Get a story's comments as nested trees: a page of top-level comments, each with
+/// its replies down to `depth` levels, sorted the same way at every level
+/// GET /api/stories/:id/comments/tree
+pub async fn get_comment_tree(
+    State(pool): State<PgPool>,
+    Path(story_id): Path<Uuid>,
+    Query(filter): Query<CommentTreeFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    // Verify story exists and hasn't been hidden by moderation
+    sqlx::query("SELECT id FROM stories WHERE id = $1 AND hidden_at IS NULL")
+        .bind(story_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("Story not found".to_string()))?;
+
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let depth = filter.depth.unwrap_or(3).clamp(1, 10);
+    let replies_limit = filter.replies_limit.unwrap_or(5).clamp(1, 50);
+    let sort = filter.sort.as_deref();
+    let by_claps = sort == Some("claps");
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, by_claps))
+        .transpose()?;
+
+    let (order_clause, keyset_clause) = comment_sort_clauses(sort);
+    let keyset_clause = if cursor.is_some() { keyset_clause } else { "" };
+    // The depth limit comes after whatever the keyset clause binds
+    let depth_param = match (&cursor, by_claps) {
+        (None, _) => 4,
+        (Some(_), false) => 6,
+        (Some(_), true) => 7,
+    };
+
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM comments WHERE story_id = $1 AND parent_id IS NULL AND hidden_at IS NULL",
+    )
+    .bind(story_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    // Walk down from a page of top-level comments, taking the first `replies_limit`
+    // replies of every comment in sort order, until `depth` levels are loaded.
+    // `position` keeps each comment's place among its siblings.
+    let query_str = format!(
+        r#"
+        WITH RECURSIVE tree AS (
+            SELECT * FROM (
+                SELECT c.id, 1 AS depth, ROW_NUMBER() OVER (ORDER BY {order}) AS position
+                FROM comments c
+                WHERE c.story_id = $1 AND c.parent_id IS NULL AND c.hidden_at IS NULL {keyset}
+                ORDER BY {order}
+                LIMIT $2
+            ) roots
+            UNION ALL
+            SELECT replies.id, tree.depth + 1, replies.position
+            FROM tree
+            CROSS JOIN LATERAL (
+                SELECT c.id, ROW_NUMBER() OVER (ORDER BY {order}) AS position
+                FROM comments c
+                WHERE c.parent_id = tree.id AND c.hidden_at IS NULL
+                ORDER BY {order}
+                LIMIT $3
+            ) replies
+            WHERE tree.depth < ${depth_param}
+        )
+        SELECT
+            c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count,
+            c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+            COALESCE(c.id = (SELECT pinned_comment_id FROM stories WHERE id = c.story_id) AND c.deleted_at IS NULL, FALSE) as is_pinned,
+            comment_mentions(c.id) as mentions,
+            u.username, u.image,
+            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count,
+            tree.depth
+        FROM tree
+        JOIN comments c ON c.id = tree.id
+        LEFT JOIN users u ON c.author_id = u.id
+        ORDER BY tree.depth, tree.position
+        "#,
+        order = order_clause,
+        keyset = keyset_clause,
+        depth_param = depth_param
+    );
+
+    // Fetch one extra top-level comment to know whether there is a next page
+    let mut query = sqlx::query_as::<_, CommentTreeRow>(&query_str)
+        .bind(story_id)
+        .bind(limit + 1)
+        .bind(replies_limit);
+    if let Some(cursor) = cursor {
+        if by_claps {
+            query = query.bind(cursor.clap_count);
+        }
+        query = query.bind(cursor.timestamp).bind(cursor.id);
+    }
+    let rows = query.bind(depth).fetch_all(&pool).await.map_err(|e| {
+        tracing::error!("Failed to fetch comment tree: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    // Rows come level by level in sibling order, so pushing keeps each list sorted
+    let mut roots = Vec::new();
+    let mut replies: HashMap<Uuid, Vec<CommentFromDb>> = HashMap::new();
+    for row in rows {
+        match row.comment.parent_id {
+            Some(parent_id) if row.depth > 1 => {
+                replies.entry(parent_id).or_default().push(row.comment)
+            }
+            _ => roots.push(row.comment),
+        }
+    }
+
+    let pagination = Pagination::from_rows(&mut roots, limit, |c| comment_cursor(c, by_claps));
+    let comments = roots
+        .into_iter()
+        .map(|c| build_comment_node(c, &mut replies, by_claps))
+        .collect();
+
+    Ok(ApiResponse::paginated(
+        CommentTreeResponse { comments, total },
+        pagination,
+    ))
 }
 
 /// Get replies to a specific comment
@@ -201,28 +537,39 @@ pub async fn get_comment_replies(
     Query(filter): Query<CommentFilter>,
 ) -> Result<impl IntoResponse, AppError> {
     // Verify parent comment exists
-    sqlx::query("SELECT id FROM comments WHERE id = $1")
+    sqlx::query("SELECT id FROM comments WHERE id = $1 AND hidden_at IS NULL")
         .bind(comment_id)
         .fetch_optional(&pool)
         .await
         .map_err(|_| AppError::InternalServerError)?
         .ok_or(AppError::NotFound("Comment not found".to_string()))?;
 
-    let limit = filter.limit.unwrap_or(20).min(100);
-    let offset = filter.offset.unwrap_or(0);
-
-    let order_clause = match filter.sort.as_deref() {
-        Some("oldest") => "c.created_at ASC",
-        Some("claps") => "c.clap_count DESC, c.created_at DESC",
-        _ => "c.created_at DESC",
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let sort = filter.sort.as_deref();
+    let by_claps = sort == Some("claps");
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, by_claps))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        filter.offset.unwrap_or(0)
     };
 
+    let (order_clause, keyset_clause) = comment_sort_clauses(sort);
+    let keyset_clause = if cursor.is_some() { keyset_clause } else { "" };
+
     // Get total count of replies
-    let total_row = sqlx::query("SELECT COUNT(*) as count FROM comments WHERE parent_id = $1")
-        .bind(comment_id)
-        .fetch_one(&pool)
-        .await
-        .map_err(|_| AppError::InternalServerError)?;
+    let total_row = sqlx::query(
+        "SELECT COUNT(*) as count FROM comments WHERE parent_id = $1 AND hidden_at IS NULL",
+    )
+    .bind(comment_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
 
     let total: i64 = total_row.get("count");
 
@@ -230,39 +577,49 @@ pub async fn get_comment_replies(
         r#"
         SELECT 
             c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count, 
-            c.created_at, c.updated_at,
+            c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+            COALESCE(c.id = (SELECT pinned_comment_id FROM stories WHERE id = c.story_id) AND c.deleted_at IS NULL, FALSE) as is_pinned,
+            comment_mentions(c.id) as mentions,
             u.username, u.image,
-            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id) as replies_count
+            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count
         FROM comments c
-        JOIN users u ON c.author_id = u.id
-        WHERE c.parent_id = $1
+        LEFT JOIN users u ON c.author_id = u.id
+        WHERE c.parent_id = $1 AND c.hidden_at IS NULL {}
         ORDER BY {}
         LIMIT $2 OFFSET $3
         "#,
-        order_clause
+        keyset_clause, order_clause
     );
 
-    let replies = sqlx::query_as::<_, CommentFromDb>(&query_str)
+    // Fetch one extra row to know whether there is a next page
+    let mut query = sqlx::query_as::<_, CommentFromDb>(&query_str)
         .bind(comment_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&pool)
-        .await
-        .map_err(|e| {
-            tracing::error!("Failed to fetch replies: {:?}", e);
-            AppError::InternalServerError
-        })?;
+        .bind(limit + 1)
+        .bind(offset);
+    if let Some(cursor) = cursor {
+        if by_claps {
+            query = query.bind(cursor.clap_count);
+        }
+        query = query.bind(cursor.timestamp).bind(cursor.id);
+    }
+
+    let mut replies = query.fetch_all(&pool).await.map_err(|e| {
+        tracing::error!("Failed to fetch replies: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let pagination = Pagination::from_rows(&mut replies, limit, |c| comment_cursor(c, by_claps));
 
     let replies_response: Vec<CommentResponse> =
         replies.into_iter().map(CommentResponse::from).collect();
 
-    let has_more = (offset + limit) < total;
-
-    Ok(ApiResponse::success(CommentsListResponse {
-        comments: replies_response,
-        total,
-        has_more,
-    }))
+    Ok(ApiResponse::paginated(
+        CommentsListResponse {
+            comments: replies_response,
+            total,
+        },
+        pagination,
+    ))
 }
 
 /// Get a single comment with its replies (threaded view)
@@ -276,12 +633,14 @@ pub async fn get_comment(
         r#"
         SELECT 
             c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count, 
-            c.created_at, c.updated_at,
+            c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+            COALESCE(c.id = (SELECT pinned_comment_id FROM stories WHERE id = c.story_id) AND c.deleted_at IS NULL, FALSE) as is_pinned,
+            comment_mentions(c.id) as mentions,
             u.username, u.image,
-            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id) as replies_count
+            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count
         FROM comments c
-        JOIN users u ON c.author_id = u.id
-        WHERE c.id = $1
+        LEFT JOIN users u ON c.author_id = u.id
+        WHERE c.id = $1 AND c.hidden_at IS NULL
         "#,
     )
     .bind(comment_id)
@@ -295,12 +654,14 @@ pub async fn get_comment(
         r#"
         SELECT 
             c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count, 
-            c.created_at, c.updated_at,
+            c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+            COALESCE(c.id = (SELECT pinned_comment_id FROM stories WHERE id = c.story_id) AND c.deleted_at IS NULL, FALSE) as is_pinned,
+            comment_mentions(c.id) as mentions,
             u.username, u.image,
-            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id) as replies_count
+            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count
         FROM comments c
-        JOIN users u ON c.author_id = u.id
-        WHERE c.parent_id = $1
+        LEFT JOIN users u ON c.author_id = u.id
+        WHERE c.parent_id = $1 AND c.hidden_at IS NULL
         ORDER BY c.created_at ASC
         LIMIT 5
         "#,
@@ -320,10 +681,13 @@ pub async fn get_comment(
     }))
 }
 
-/// Update a comment (author only)
+/// Update a comment (author only, within the edit window after posting).
+/// The previous content is kept in the comment's edit history.
 /// PUT /api/comments/:id
 pub async fn update_comment(
     State(pool): State<PgPool>,
+    State(settings): State<Settings>,
+    State(email_service): State<EmailService>,
     claims: jwt::Claims,
     Path(comment_id): Path<Uuid>,
     Json(payload): Json<UpdateComment>,
@@ -332,57 +696,465 @@ pub async fn update_comment(
         .validate()
         .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
 
-    // Verify ownership
-    let row = sqlx::query("SELECT author_id FROM comments WHERE id = $1")
-        .bind(comment_id)
-        .fetch_optional(&pool)
+    let mut tx = pool
+        .begin()
         .await
-        .map_err(|_| AppError::InternalServerError)?
-        .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Verify ownership; deleted comments can't be edited
+    let row = sqlx::query(
+        "SELECT author_id, content, created_at, edited_at FROM comments WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+    )
+    .bind(comment_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Comment not found".to_string()))?;
 
     let author_id: Uuid = row.get("author_id");
     if author_id != claims.sub {
         return Err(AppError::Unauthorized);
     }
 
-    sqlx::query("UPDATE comments SET content = $1, updated_at = NOW() WHERE id = $2")
+    let created_at: DateTime<Utc> = row.get("created_at");
+    if Utc::now() > created_at + Duration::seconds(settings.comment_edit_window_secs) {
+        return Err(AppError::UnprocessableEntity(format!(
+            "Comments can only be edited within {} minutes of posting",
+            settings.comment_edit_window_secs / 60
+        )));
+    }
+
+    let content: String = row.get("content");
+    if content != payload.content {
+        let edited_at: Option<DateTime<Utc>> = row.get("edited_at");
+
+        sqlx::query(
+            "INSERT INTO comment_edits (comment_id, content, written_at) VALUES ($1, $2, $3)",
+        )
+        .bind(comment_id)
+        .bind(&content)
+        .bind(edited_at.unwrap_or(created_at))
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+        sqlx::query(
+            "UPDATE comments SET content = $1, edited_at = NOW(), updated_at = NOW() WHERE id = $2",
+        )
         .bind(&payload.content)
         .bind(comment_id)
-        .execute(&pool)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+        mentions::sync_comment(&mut tx, comment_id, &payload.content).await?;
+    }
+
+    tx.commit()
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
+    // Only people newly mentioned by the edit hear about it
+    mentions::notify(&pool, &email_service, MentionTarget::Comment(comment_id)).await;
+
     get_comment_response(&pool, comment_id).await
 }
 
-/// Delete a comment (author only)
-/// DELETE /api/comments/:id
+/// Get a comment's edit history, newest version first. Public for visible comments;
+/// hidden and deleted ones are only shown to moderators.
+/// GET /api/comments/:id/history
+pub async fn get_comment_history(
+    State(pool): State<PgPool>,
+    claims: Option<jwt::Claims>,
+    Path(comment_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let is_moderator = claims.is_some_and(|c| c.role >= Role::Moderator);
+
+    let visible: bool = sqlx::query_scalar(
+        "SELECT hidden_at IS NULL AND deleted_at IS NULL FROM comments WHERE id = $1",
+    )
+    .bind(comment_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    if !visible && !is_moderator {
+        return Err(AppError::NotFound("Comment not found".to_string()));
+    }
+
+    let versions = sqlx::query_as::<_, CommentVersion>(
+        r#"
+        SELECT content, COALESCE(edited_at, created_at) as written_at, NULL::timestamptz as replaced_at
+        FROM comments WHERE id = $1
+        UNION ALL
+        SELECT content, written_at, replaced_at
+        FROM comment_edits WHERE comment_id = $1
+        ORDER BY written_at DESC
+        "#,
+    )
+    .bind(comment_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch comment history: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(ApiResponse::success(versions))
+}
+
+/// Delete a comment (author, or a moderator with an audit reason).
+/// A comment with replies becomes a "[deleted]" tombstone so the thread below it
+/// survives; a moderator's removal shows "[removed by a moderator]" instead and
+/// keeps the original content for review. Comments without replies are deleted.
+/// DELETE /api/comments/:id?reason=...
 pub async fn delete_comment(
     State(pool): State<PgPool>,
     claims: jwt::Claims,
     Path(comment_id): Path<Uuid>,
+    Query(moderation): Query<ModerationReason>,
 ) -> Result<impl IntoResponse, AppError> {
-    // Verify ownership
-    let row = sqlx::query("SELECT author_id FROM comments WHERE id = $1")
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Verify ownership; the lock keeps a reply from arriving between the check and the delete
+    let row = sqlx::query(
+        r#"
+        SELECT author_id, parent_id,
+            EXISTS(SELECT 1 FROM comments WHERE parent_id = $1) as has_replies
+        FROM comments
+        WHERE id = $1 AND deleted_at IS NULL
+        FOR UPDATE
+        "#,
+    )
+    .bind(comment_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    let author_id: Uuid = row.get("author_id");
+    let parent_id: Option<Uuid> = row.get("parent_id");
+    let has_replies: bool = row.get("has_replies");
+    let is_author = author_id == claims.sub;
+    if !is_author && claims.role < Role::Moderator {
+        return Err(AppError::Unauthorized);
+    }
+
+    // Moderators removing someone else's comment must leave an audit reason
+    if !is_author {
+        let reason = moderation::require_reason(moderation.reason)?;
+        moderation::log_action(
+            &mut *tx,
+            claims.sub,
+            ModerationAction::DeleteComment,
+            comment_id,
+            Some(author_id),
+            &reason,
+        )
+        .await?;
+    }
+
+    if has_replies {
+        let deleted_by = if is_author {
+            CommentDeletion::Author
+        } else {
+            CommentDeletion::Moderator
+        };
+
+        // The wiped content goes into the edit history like an edit would, which only
+        // moderators can see once the comment is deleted; the mentions go with it
+        if is_author {
+            sqlx::query(
+                r#"
+                INSERT INTO comment_edits (comment_id, content, written_at)
+                SELECT id, content, COALESCE(edited_at, created_at) FROM comments WHERE id = $1
+                "#,
+            )
+            .bind(comment_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+            sqlx::query("DELETE FROM mentions WHERE comment_id = $1")
+                .bind(comment_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| AppError::InternalServerError)?;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE comments SET
+                deleted_at = NOW(),
+                deleted_by = $2,
+                content = CASE WHEN $2 = 'author'::comment_deletion THEN '[deleted]' ELSE content END
+            WHERE id = $1
+            "#,
+        )
         .bind(comment_id)
+        .bind(deleted_by)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+        // A deleted comment can't stay pinned
+        sqlx::query("UPDATE stories SET pinned_comment_id = NULL WHERE pinned_comment_id = $1")
+            .bind(comment_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+    } else {
+        sqlx::query("DELETE FROM comments WHERE id = $1")
+            .bind(comment_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+        // Tombstones left without replies have nothing more to hold up
+        let mut parent_id = parent_id;
+        while let Some(id) = parent_id {
+            parent_id = sqlx::query_scalar::<_, Option<Uuid>>(
+                r#"
+                DELETE FROM comments
+                WHERE id = $1 AND deleted_at IS NOT NULL
+                    AND NOT EXISTS(SELECT 1 FROM comments WHERE parent_id = $1)
+                RETURNING parent_id
+                "#,
+            )
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?
+            .flatten();
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::ok("Comment deleted".to_string()))
+}
+
+/// Get who may comment on a story and which comment is pinned
+/// GET /api/stories/:id/comment-settings
+pub async fn get_comment_settings(
+    State(pool): State<PgPool>,
+    Path(story_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let settings = sqlx::query_as::<_, CommentSettings>(
+        r#"
+        SELECT id as story_id, comment_policy as policy, pinned_comment_id
+        FROM stories WHERE id = $1 AND hidden_at IS NULL
+        "#,
+    )
+    .bind(story_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Story not found".to_string()))?;
+
+    Ok(ApiResponse::success(settings))
+}
+
+/// Choose who may comment on a story: everyone, only the author's followers, or nobody
+/// (story author only). Existing comments stay visible.
+/// PUT /api/stories/:id/comment-settings
+pub async fn update_comment_settings(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(story_id): Path<Uuid>,
+    Json(payload): Json<UpdateCommentSettings>,
+) -> Result<impl IntoResponse, AppError> {
+    let author_id: Uuid = sqlx::query_scalar("SELECT author_id FROM stories WHERE id = $1")
+        .bind(story_id)
         .fetch_optional(&pool)
         .await
         .map_err(|_| AppError::InternalServerError)?
-        .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+        .ok_or(AppError::NotFound("Story not found".to_string()))?;
 
-    let author_id: Uuid = row.get("author_id");
     if author_id != claims.sub {
         return Err(AppError::Unauthorized);
     }
 
-    // Delete comment (cascades to replies due to FK constraint)
-    sqlx::query("DELETE FROM comments WHERE id = $1")
+    let settings = sqlx::query_as::<_, CommentSettings>(
+        r#"
+        UPDATE stories SET comment_policy = $2 WHERE id = $1
+        RETURNING id as story_id, comment_policy as policy, pinned_comment_id
+        "#,
+    )
+    .bind(story_id)
+    .bind(payload.policy)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update comment settings: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(ApiResponse::success(settings))
+}
+
+/// Pin a top-level comment to the top of a story's comments, replacing any pinned
+/// before it (story author only)
+/// POST /api/comments/:id/pin
+pub async fn pin_comment(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(comment_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let row = sqlx::query(
+        r#"
+        SELECT c.story_id, c.parent_id, s.author_id as story_author_id
+        FROM comments c
+        JOIN stories s ON c.story_id = s.id
+        WHERE c.id = $1 AND c.hidden_at IS NULL AND c.deleted_at IS NULL
+        "#,
+    )
+    .bind(comment_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    let story_author_id: Uuid = row.get("story_author_id");
+    if story_author_id != claims.sub {
+        return Err(AppError::Unauthorized);
+    }
+
+    let parent_id: Option<Uuid> = row.get("parent_id");
+    if parent_id.is_some() {
+        return Err(AppError::UnprocessableEntity(
+            "Only top-level comments can be pinned".to_string(),
+        ));
+    }
+
+    let story_id: Uuid = row.get("story_id");
+    sqlx::query("UPDATE stories SET pinned_comment_id = $1 WHERE id = $2")
         .bind(comment_id)
+        .bind(story_id)
         .execute(&pool)
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    Ok(ApiResponse::ok("Comment deleted".to_string()))
+    get_comment_response(&pool, comment_id).await
+}
+
+/// Unpin a comment (story author only)
+/// DELETE /api/comments/:id/pin
+pub async fn unpin_comment(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(comment_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let story_author_id: Uuid = sqlx::query_scalar(
+        "SELECT s.author_id FROM comments c JOIN stories s ON c.story_id = s.id WHERE c.id = $1",
+    )
+    .bind(comment_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    if story_author_id != claims.sub {
+        return Err(AppError::Unauthorized);
+    }
+
+    sqlx::query("UPDATE stories SET pinned_comment_id = NULL WHERE pinned_comment_id = $1")
+        .bind(comment_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::ok("Comment unpinned".to_string()))
+}
+
+/// Hide or unhide a comment on one of your stories (story author only). Hidden comments
+/// drop out of the story's comments like moderator-hidden ones, but authors can only
+/// unhide the comments they hid themselves.
+/// PUT /api/comments/:id/visibility
+pub async fn set_comment_hidden(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(comment_id): Path<Uuid>,
+    Json(payload): Json<HideCommentRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let row = sqlx::query(
+        r#"
+        SELECT s.author_id as story_author_id, c.hidden_at IS NOT NULL as hidden, c.hidden_by_author
+        FROM comments c
+        JOIN stories s ON c.story_id = s.id
+        WHERE c.id = $1 AND c.deleted_at IS NULL
+        FOR UPDATE OF c
+        "#,
+    )
+    .bind(comment_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    let story_author_id: Uuid = row.get("story_author_id");
+    if story_author_id != claims.sub {
+        return Err(AppError::Unauthorized);
+    }
+
+    let hidden: bool = row.get("hidden");
+    let hidden_by_author: bool = row.get("hidden_by_author");
+
+    match (payload.hidden, hidden) {
+        (true, false) => {
+            sqlx::query(
+                "UPDATE comments SET hidden_at = NOW(), hidden_by_author = TRUE WHERE id = $1",
+            )
+            .bind(comment_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+
+            // A hidden comment can't stay pinned
+            sqlx::query("UPDATE stories SET pinned_comment_id = NULL WHERE pinned_comment_id = $1")
+                .bind(comment_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|_| AppError::InternalServerError)?;
+        }
+        (false, true) => {
+            if !hidden_by_author {
+                return Err(AppError::UnprocessableEntity(
+                    "This comment was hidden by a moderator".to_string(),
+                ));
+            }
+
+            sqlx::query(
+                "UPDATE comments SET hidden_at = NULL, hidden_by_author = FALSE WHERE id = $1",
+            )
+            .bind(comment_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?;
+        }
+        // Already as requested
+        _ => {}
+    }
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(VisibilityResponse {
+        id: comment_id,
+        hidden: payload.hidden,
+    }))
 }
 
 /// Clap on a comment
@@ -398,12 +1170,16 @@ pub async fn clap_comment(
         .map_err(|_| AppError::InternalServerError)?;
 
     // Check if comment exists
-    sqlx::query("SELECT id FROM comments WHERE id = $1")
-        .bind(comment_id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|_| AppError::InternalServerError)?
-        .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+    let comment =
+        sqlx::query("SELECT author_id, story_id FROM comments WHERE id = $1 AND hidden_at IS NULL AND deleted_at IS NULL")
+            .bind(comment_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?
+            .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    let comment_author_id: Uuid = comment.get("author_id");
+    let story_id: Uuid = comment.get("story_id");
 
     // Check existing claps
     let current_claps_row =
@@ -448,6 +1224,16 @@ pub async fn clap_comment(
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
+    notifications::notify(
+        &pool,
+        comment_author_id,
+        claims.sub,
+        NotificationType::CommentClap,
+        Some(story_id),
+        Some(comment_id),
+    )
+    .await;
+
     // Return updated comment
     get_comment_response(&pool, comment_id).await
 }
@@ -461,11 +1247,13 @@ async fn get_comment_response(
         r#"
         SELECT 
             c.id, c.story_id, c.author_id, c.parent_id, c.content, c.clap_count, 
-            c.created_at, c.updated_at,
+            c.created_at, c.updated_at, c.edited_at, c.deleted_by,
+            COALESCE(c.id = (SELECT pinned_comment_id FROM stories WHERE id = c.story_id) AND c.deleted_at IS NULL, FALSE) as is_pinned,
+            comment_mentions(c.id) as mentions,
             u.username, u.image,
-            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id) as replies_count
+            (SELECT COUNT(*) FROM comments WHERE parent_id = c.id AND hidden_at IS NULL) as replies_count
         FROM comments c
-        JOIN users u ON c.author_id = u.id
+        LEFT JOIN users u ON c.author_id = u.id
         WHERE c.id = $1
         "#,
     )
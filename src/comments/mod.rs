@@ -1,9 +1,41 @@
 use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::mentions::MentionSpan;
+
 pub mod handler;
 
+/// Who removed a tombstoned comment
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "comment_deletion", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CommentDeletion {
+    Author,
+    Moderator,
+}
+
+impl CommentDeletion {
+    /// What a tombstoned comment shows instead of its content
+    pub fn placeholder(self) -> &'static str {
+        match self {
+            CommentDeletion::Author => "[deleted]",
+            CommentDeletion::Moderator => "[removed by a moderator]",
+        }
+    }
+}
+
+/// Who may comment on a story, as chosen by its author
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "comment_policy", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum CommentPolicy {
+    Everyone,
+    Followers, // Only the author's followers
+    Disabled,
+}
+
 /// Database model for a comment
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Comment {
@@ -40,20 +72,54 @@ pub struct UpdateComment {
     pub content: String,
 }
 
+/// Request payload for changing a story's comment settings
+#[derive(Debug, Deserialize)]
+pub struct UpdateCommentSettings {
+    pub policy: CommentPolicy,
+}
+
+/// A story's comment settings
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CommentSettings {
+    pub story_id: Uuid,
+    pub policy: CommentPolicy,
+    pub pinned_comment_id: Option<Uuid>,
+}
+
+/// Request payload for a story author hiding or unhiding a comment
+#[derive(Debug, Deserialize)]
+pub struct HideCommentRequest {
+    pub hidden: bool,
+}
+
 /// Response structure for a comment with author info
 #[derive(Debug, Serialize)]
 pub struct CommentResponse {
     pub id: Uuid,
     pub story_id: Uuid,
-    pub author: CommentAuthor,
+    pub author: Option<CommentAuthor>, // Hidden once the comment is deleted
     pub parent_id: Option<Uuid>,
     pub content: String,
+    pub mentions: Vec<MentionSpan>, // @username mentions in the content
     pub clap_count: i32,
     pub replies_count: i64,
+    /// Deleted comments with replies stay in the thread as "[deleted]" placeholders
+    pub is_deleted: bool,
+    pub is_pinned: bool, // Pinned by the story's author
+    pub is_edited: bool,
+    pub edited_at: Option<chrono::DateTime<chrono::Utc>>, // Last content edit
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// One version of a comment's content, for its edit history
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct CommentVersion {
+    pub content: String,
+    pub written_at: chrono::DateTime<chrono::Utc>,
+    pub replaced_at: Option<chrono::DateTime<chrono::Utc>>, // Null for the current version
+}
+
 /// Author info embedded in comment response
 #[derive(Debug, Serialize)]
 pub struct CommentAuthor {
@@ -66,16 +132,26 @@ pub struct CommentAuthor {
 #[derive(Debug, Deserialize)]
 pub struct CommentFilter {
     pub limit: Option<i64>,
-    pub offset: Option<i64>,
+    pub offset: Option<i64>, // Legacy, prefer cursor
+    pub cursor: Option<String>,
     pub sort: Option<String>, // "latest", "oldest", or "claps"
 }
 
+/// Query parameters for fetching a story's comments as a tree
+#[derive(Debug, Deserialize)]
+pub struct CommentTreeFilter {
+    pub limit: Option<i64>, // Top-level comments per page
+    pub cursor: Option<String>,
+    pub sort: Option<String>, // "latest", "oldest", or "claps", applied at every level
+    pub depth: Option<i32>,   // Levels to include, top level being 1
+    pub replies_limit: Option<i64>, // Replies included per comment
+}
+
 /// Response for paginated comments list
 #[derive(Debug, Serialize)]
 pub struct CommentsListResponse {
     pub comments: Vec<CommentResponse>,
     pub total: i64,
-    pub has_more: bool,
 }
 
 /// Nested comment structure with replies
@@ -85,3 +161,23 @@ pub struct CommentWithReplies {
     pub comment: CommentResponse,
     pub replies: Vec<CommentResponse>,
 }
+
+/// A comment with its replies nested down to the requested depth
+#[derive(Debug, Serialize)]
+pub struct CommentNode {
+    #[serde(flatten)]
+    pub comment: CommentResponse,
+    pub replies: Vec<CommentNode>,
+    /// Whether the comment has more replies than are included
+    pub has_more_replies: bool,
+    /// Pass as `cursor` (with the same `sort`) to GET /api/comments/:id/replies for
+    /// the rest; `null` with `has_more_replies` means start from the first reply
+    pub replies_cursor: Option<String>,
+}
+
+/// Response for a page of comment trees
+#[derive(Debug, Serialize)]
+pub struct CommentTreeResponse {
+    pub comments: Vec<CommentNode>,
+    pub total: i64,
+}
@@ -1,6 +1,70 @@
 use std::env;
 use std::net::SocketAddr;
 
+/// Token bucket size and refill rate for one rate-limited route group
+#[derive(Clone, Copy)]
+pub struct RateLimitConfig {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl RateLimitConfig {
+    /// Read `{PREFIX}_BURST` and `{PREFIX}_PER_MINUTE`, falling back to the given defaults
+    fn from_env(prefix: &str, burst: u32, per_minute: u32) -> Self {
+        let read = |suffix: &str, default: u32| {
+            env::var(format!("{}_{}", prefix, suffix))
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            burst: read("BURST", burst),
+            per_minute: read("PER_MINUTE", per_minute),
+        }
+    }
+}
+
+/// An OAuth2 / OpenID Connect provider users can sign in with
+#[derive(Clone)]
+pub struct OAuthProviderConfig {
+    /// Name used in the API paths, e.g. `google`
+    pub name: String,
+    /// Issuer URL; endpoints and signing keys come from its discovery document
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: String,
+    pub redirect_uri: String,
+    /// Whether the provider's verified emails can be trusted: they mark new accounts
+    /// as verified and link to existing accounts with the same email
+    pub trusted_email: bool,
+}
+
+impl OAuthProviderConfig {
+    /// Read `OAUTH_{NAME}_*` for a provider listed in `OAUTH_PROVIDERS`
+    fn from_env(name: &str, frontend_url: &str) -> Option<Self> {
+        let prefix = format!("OAUTH_{}", name.to_uppercase());
+        let read = |suffix: &str| env::var(format!("{}_{}", prefix, suffix)).ok();
+
+        let (Some(issuer), Some(client_id)) = (read("ISSUER"), read("CLIENT_ID")) else {
+            tracing::warn!("OAuth provider {} is missing an issuer or client id", name);
+            return None;
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            issuer,
+            client_id,
+            client_secret: read("CLIENT_SECRET").unwrap_or_default(),
+            scopes: read("SCOPES").unwrap_or_else(|| "openid email profile".to_string()),
+            redirect_uri: read("REDIRECT_URI")
+                .unwrap_or_else(|| format!("{}/oauth/{}/callback", frontend_url, name)),
+            trusted_email: read("TRUSTED_EMAIL").is_some_and(|s| s == "true"),
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct Settings {
     pub port: u16,
@@ -15,6 +79,29 @@ pub struct Settings {
     pub from_email: String,
     pub from_name: String,
     pub frontend_url: String,
+    // Background jobs
+    pub publish_interval_secs: u64,
+    pub export_interval_secs: u64,
+    pub purge_interval_secs: u64,
+    // Account deletion and data export
+    pub deletion_grace_days: i64,
+    pub export_ttl_days: i64,
+    pub export_timeout_secs: i64,
+    // Comments
+    pub comment_edit_window_secs: i64,
+    // Rate limits
+    pub rate_limit_auth: RateLimitConfig,
+    pub rate_limit_email: RateLimitConfig,
+    pub rate_limit_claps: RateLimitConfig,
+    pub rate_limit_comments: RateLimitConfig,
+    // Account lockout
+    pub lockout_max_failures: i32,
+    pub lockout_base_secs: i64,
+    pub lockout_max_secs: i64,
+    pub lockout_ip_max_failures: i64,
+    pub lockout_ip_window_secs: i64,
+    // Social sign-in
+    pub oauth_providers: Vec<OAuthProviderConfig>,
 }
 
 impl Settings {
@@ -42,6 +129,90 @@ impl Settings {
         let frontend_url =
             env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
 
+        // How often the scheduled story publisher runs
+        let publish_interval_secs: u64 = env::var("PUBLISH_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(30);
+
+        // How often pending data exports are picked up
+        let export_interval_secs: u64 = env::var("EXPORT_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(10);
+
+        // How often accounts past their deletion grace period are removed
+        let purge_interval_secs: u64 = env::var("PURGE_INTERVAL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(3600);
+
+        // Days between a deletion request and the data actually being removed
+        let deletion_grace_days: i64 = env::var("DELETION_GRACE_DAYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(14);
+
+        // Days a finished data export stays downloadable
+        let export_ttl_days: i64 = env::var("EXPORT_TTL_DAYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(7);
+
+        // How long an export can stay 'processing' before it is taken to be abandoned
+        let export_timeout_secs: i64 = env::var("EXPORT_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(900);
+
+        // How long after posting a comment can still be edited
+        let comment_edit_window_secs: i64 = env::var("COMMENT_EDIT_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(3600);
+
+        // Sign-in and other credential endpoints, per client IP
+        let rate_limit_auth = RateLimitConfig::from_env("RATE_LIMIT_AUTH", 10, 10);
+        // Endpoints that send email, per client IP
+        let rate_limit_email = RateLimitConfig::from_env("RATE_LIMIT_EMAIL", 3, 1);
+        // Story and comment claps, per user
+        let rate_limit_claps = RateLimitConfig::from_env("RATE_LIMIT_CLAPS", 30, 60);
+        // New comments, per user
+        let rate_limit_comments = RateLimitConfig::from_env("RATE_LIMIT_COMMENTS", 5, 10);
+
+        // Lock an account after this many consecutive failed sign-ins; the lockout
+        // starts at the base duration and doubles with every further lockout
+        let lockout_max_failures: i32 = env::var("LOCKOUT_MAX_FAILURES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(5);
+        let lockout_base_secs: i64 = env::var("LOCKOUT_BASE_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(300);
+        let lockout_max_secs: i64 = env::var("LOCKOUT_MAX_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(86400);
+        // Refuse sign-ins from an IP with this many failures inside the window
+        let lockout_ip_max_failures: i64 = env::var("LOCKOUT_IP_MAX_FAILURES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(20);
+        let lockout_ip_window_secs: i64 = env::var("LOCKOUT_IP_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(900);
+
+        // Comma-separated provider names, each configured through OAUTH_{NAME}_* variables
+        let oauth_providers = env::var("OAUTH_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| OAuthProviderConfig::from_env(name, &frontend_url))
+            .collect();
+
         Self {
             port,
             addr,
@@ -54,6 +225,28 @@ impl Settings {
             from_email,
             from_name,
             frontend_url,
+            publish_interval_secs,
+            export_interval_secs,
+            purge_interval_secs,
+            deletion_grace_days,
+            export_ttl_days,
+            export_timeout_secs,
+            comment_edit_window_secs,
+            rate_limit_auth,
+            rate_limit_email,
+            rate_limit_claps,
+            rate_limit_comments,
+            lockout_max_failures,
+            lockout_base_secs,
+            lockout_max_secs,
+            lockout_ip_max_failures,
+            lockout_ip_window_secs,
+            oauth_providers,
         }
     }
+
+    /// Look up a configured OAuth provider by name
+    pub fn oauth_provider(&self, name: &str) -> Option<&OAuthProviderConfig> {
+        self.oauth_providers.iter().find(|p| p.name == name)
+    }
 }
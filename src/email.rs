@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use lettre::{
     message::{header::ContentType, MultiPart, SinglePart},
     transport::smtp::authentication::Credentials,
@@ -7,6 +8,7 @@ use lettre::{
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct EmailService {
@@ -114,6 +116,121 @@ Start exploring: {}
 
 Happy writing!
 
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "account_locked" => format!(
+                r#"Hi {},
+
+We noticed several failed sign-in attempts on your account, so we've temporarily locked it to keep it safe. You can sign in again once the lock lifts.
+
+If this wasn't you, we recommend resetting your password:
+
+{}
+
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "magic_link" => format!(
+                r#"Hi {},
+
+Click the link below to sign in to BlogVerse, no password needed:
+
+{}
+
+This link will expire in 15 minutes and can only be used once.
+
+If you didn't ask to sign in, you can safely ignore this email.
+
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "password_changed" => format!(
+                r#"Hi {},
+
+The password for your BlogVerse account was just changed and your other devices have been signed out.
+
+If you made this change, there's nothing else to do. If you didn't, reset your password right away:
+
+{}
+
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "email_change" => format!(
+                r#"Hi there,
+
+We received a request to use this address for your BlogVerse account. Confirm it by clicking the link below:
+
+{}
+
+This link will expire in 24 hours.
+
+If you didn't request this change, you can safely ignore this email.
+
+Best regards,
+The BlogVerse Team"#,
+                link
+            ),
+            "email_changed" => format!(
+                r#"Hi {},
+
+The email address on your BlogVerse account was just changed. Emails about your account will go to the new address from now on.
+
+If you made this change, there's nothing else to do. If you didn't, reset your password right away and contact support:
+
+{}
+
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "account_deletion" => format!(
+                r#"Hi {},
+
+We received a request to delete your BlogVerse account. Your account and everything in it will be removed permanently once the grace period ends.
+
+Changed your mind? Just sign in again before then and the deletion is cancelled:
+
+{}
+
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "export_ready" => format!(
+                r#"Hi {},
+
+The export of your BlogVerse data is ready. Download it here:
+
+{}
+
+The download will be available for a limited time.
+
+Best regards,
+The BlogVerse Team"#,
+                username.unwrap_or("there"),
+                link
+            ),
+            "mention" => format!(
+                r#"Hi {},
+
+You were mentioned on BlogVerse. See what was said:
+
+{}
+
+You can turn these emails off in your notification settings.
+
 Best regards,
 The BlogVerse Team"#,
                 username.unwrap_or("there"),
@@ -161,6 +278,31 @@ The BlogVerse Team"#,
         .await
     }
 
+    /// Send a one-time sign-in link
+    pub async fn send_magic_link_email(
+        &self,
+        to_email: &str,
+        username: &str,
+        token: &str,
+    ) -> Result<()> {
+        let magic_link = format!("{}/magic-link?token={}", self.frontend_url, token);
+
+        let mut variables = HashMap::new();
+        variables.insert("username", username.to_string());
+        variables.insert("magic_link", magic_link.clone());
+
+        let html_body = self.load_template("magic_link.html", &variables)?;
+        let plain_body = self.generate_plain_text("magic_link", &magic_link, Some(username));
+
+        self.send_email(
+            to_email,
+            "Your Sign-In Link - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
     /// Send welcome email after verification
     pub async fn send_welcome_email(&self, to_email: &str, username: &str) -> Result<()> {
         let dashboard_link = format!("{}/dashboard", self.frontend_url);
@@ -176,6 +318,198 @@ The BlogVerse Team"#,
             .await
     }
 
+    /// Notify a user that their account was locked after repeated failed sign-ins
+    pub async fn send_account_locked_email(
+        &self,
+        to_email: &str,
+        username: &str,
+        locked_until: DateTime<Utc>,
+    ) -> Result<()> {
+        let reset_link = format!("{}/forgot-password", self.frontend_url);
+
+        let mut variables = HashMap::new();
+        variables.insert("username", username.to_string());
+        variables.insert(
+            "locked_until",
+            locked_until.format("%Y-%m-%d %H:%M UTC").to_string(),
+        );
+        variables.insert("reset_link", reset_link.clone());
+
+        let html_body = self.load_template("account_locked.html", &variables)?;
+        let plain_body = self.generate_plain_text("account_locked", &reset_link, Some(username));
+
+        self.send_email(
+            to_email,
+            "Your Account Has Been Locked - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
+    /// Tell a user their password was changed
+    pub async fn send_password_changed_email(&self, to_email: &str, username: &str) -> Result<()> {
+        let reset_link = format!("{}/forgot-password", self.frontend_url);
+
+        let mut variables = HashMap::new();
+        variables.insert("username", username.to_string());
+        variables.insert("reset_link", reset_link.clone());
+
+        let html_body = self.load_template("password_changed.html", &variables)?;
+        let plain_body = self.generate_plain_text("password_changed", &reset_link, Some(username));
+
+        self.send_email(
+            to_email,
+            "Your Password Was Changed - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
+    /// Send the confirmation link for an email change to the new address
+    pub async fn send_email_change_email(&self, to_email: &str, token: &str) -> Result<()> {
+        let confirm_link = format!("{}/confirm-email-change?token={}", self.frontend_url, token);
+
+        let mut variables = HashMap::new();
+        variables.insert("confirm_link", confirm_link.clone());
+
+        let html_body = self.load_template("email_change.html", &variables)?;
+        let plain_body = self.generate_plain_text("email_change", &confirm_link, None);
+
+        self.send_email(
+            to_email,
+            "Confirm Your New Email - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
+    /// Security notice to the previous address once an email change went through
+    pub async fn send_email_changed_email(
+        &self,
+        to_email: &str,
+        username: &str,
+        new_email: &str,
+    ) -> Result<()> {
+        let reset_link = format!("{}/forgot-password", self.frontend_url);
+
+        let mut variables = HashMap::new();
+        variables.insert("username", username.to_string());
+        variables.insert("new_email", new_email.to_string());
+        variables.insert("reset_link", reset_link.clone());
+
+        let html_body = self.load_template("email_changed.html", &variables)?;
+        let plain_body = self.generate_plain_text("email_changed", &reset_link, Some(username));
+
+        self.send_email(
+            to_email,
+            "Your Email Was Changed - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
+    /// Confirm that an account is scheduled for deletion and explain how to cancel
+    pub async fn send_account_deletion_email(
+        &self,
+        to_email: &str,
+        username: &str,
+        deletion_scheduled_for: DateTime<Utc>,
+    ) -> Result<()> {
+        let sign_in_link = format!("{}/sign-in", self.frontend_url);
+
+        let mut variables = HashMap::new();
+        variables.insert("username", username.to_string());
+        variables.insert(
+            "deletion_date",
+            deletion_scheduled_for
+                .format("%Y-%m-%d %H:%M UTC")
+                .to_string(),
+        );
+        variables.insert("sign_in_link", sign_in_link.clone());
+
+        let html_body = self.load_template("account_deletion.html", &variables)?;
+        let plain_body =
+            self.generate_plain_text("account_deletion", &sign_in_link, Some(username));
+
+        self.send_email(
+            to_email,
+            "Your Account Will Be Deleted - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
+    /// Let a user know their data export can be downloaded
+    pub async fn send_export_ready_email(
+        &self,
+        to_email: &str,
+        username: &str,
+        export_id: Uuid,
+    ) -> Result<()> {
+        let download_link = format!("{}/settings/exports/{}", self.frontend_url, export_id);
+
+        let mut variables = HashMap::new();
+        variables.insert("username", username.to_string());
+        variables.insert("download_link", download_link.clone());
+
+        let html_body = self.load_template("export_ready.html", &variables)?;
+        let plain_body = self.generate_plain_text("export_ready", &download_link, Some(username));
+
+        self.send_email(
+            to_email,
+            "Your Data Export Is Ready - BlogVerse",
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
+    /// Let a user know someone mentioned them in a story or in a comment on one
+    pub async fn send_mention_email(
+        &self,
+        to_email: &str,
+        username: &str,
+        actor_username: &str,
+        story_title: &str,
+        story_slug: &str,
+        comment_id: Option<Uuid>,
+    ) -> Result<()> {
+        let (context, story_link) = match comment_id {
+            Some(comment_id) => (
+                "in a comment on",
+                format!(
+                    "{}/s/{}#comment-{}",
+                    self.frontend_url, story_slug, comment_id
+                ),
+            ),
+            None => ("in", format!("{}/s/{}", self.frontend_url, story_slug)),
+        };
+
+        // The story title and names are user-written, so they're escaped for the HTML
+        let mut variables = HashMap::new();
+        variables.insert("username", escape_html(username));
+        variables.insert("actor", escape_html(actor_username));
+        variables.insert("context", context.to_string());
+        variables.insert("story_title", escape_html(story_title));
+        variables.insert("story_link", story_link.clone());
+
+        let html_body = self.load_template("mention.html", &variables)?;
+        let plain_body = self.generate_plain_text("mention", &story_link, Some(username));
+
+        self.send_email(
+            to_email,
+            &format!("{} Mentioned You - BlogVerse", actor_username),
+            &plain_body,
+            &html_body,
+        )
+        .await
+    }
+
     /// Send multipart email (HTML + plain text fallback)
     async fn send_email(
         &self,
@@ -210,3 +544,12 @@ The BlogVerse Team"#,
         Ok(())
     }
 }
+
+/// Escape text for use inside HTML
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
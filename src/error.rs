@@ -1,5 +1,5 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -13,10 +13,17 @@ pub enum AppError {
     NotFound(String),
     Conflict(String),
     UnprocessableEntity(String),
+    /// Rate limit exceeded; the client may retry after this many seconds
+    TooManyRequests(u64),
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match self {
+            AppError::TooManyRequests(secs) => Some(secs),
+            _ => None,
+        };
+
         let (status, error_message) = match self {
             AppError::InternalServerError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -27,6 +34,10 @@ impl IntoResponse for AppError {
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
             AppError::UnprocessableEntity(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+            AppError::TooManyRequests(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, please try again later".to_string(),
+            ),
         };
 
         // Standardized failure response matching ApiResponse structure
@@ -36,6 +47,12 @@ impl IntoResponse for AppError {
             "data": null
         }));
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, header::HeaderValue::from(secs));
+        }
+        response
     }
 }
@@ -0,0 +1,124 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::jwt,
+    error::AppError,
+    exports::{ExportResponse, ExportStatus},
+    response::ApiResponse,
+};
+
+/// Request an export of all your data; it is built in the background and you get an
+/// email when it can be downloaded
+/// POST /api/auth/me/export
+pub async fn request_export(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let in_progress: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM data_exports WHERE user_id = $1 AND status IN ('pending', 'processing'))",
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    if in_progress {
+        return Err(AppError::Conflict(
+            "An export is already in progress".to_string(),
+        ));
+    }
+
+    let export = sqlx::query_as::<_, ExportResponse>(
+        r#"
+        INSERT INTO data_exports (user_id) VALUES ($1)
+        RETURNING id, status, created_at, completed_at, expires_at
+        "#,
+    )
+    .bind(claims.sub)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Database error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(ApiResponse::success(export).accepted())
+}
+
+/// Check on one of your data exports
+/// GET /api/auth/me/exports/{id}
+pub async fn get_export(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(export_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let export = sqlx::query_as::<_, ExportResponse>(
+        r#"
+        SELECT id, status, created_at, completed_at, expires_at
+        FROM data_exports
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(export_id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Export not found".to_string()))?;
+
+    Ok(ApiResponse::success(export))
+}
+
+/// Download a finished data export as a JSON file
+/// GET /api/auth/me/exports/{id}/download
+pub async fn download_export(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(export_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let (status, archive): (ExportStatus, Option<Value>) = sqlx::query_as(
+        r#"
+        SELECT status, archive FROM data_exports
+        WHERE id = $1 AND user_id = $2 AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(export_id)
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Export not found".to_string()))?;
+
+    let archive = match (status, archive) {
+        (ExportStatus::Ready, Some(archive)) => archive,
+        (ExportStatus::Failed, _) => {
+            return Err(AppError::UnprocessableEntity(
+                "This export failed, please request a new one".to_string(),
+            ))
+        }
+        _ => {
+            return Err(AppError::Conflict(
+                "This export is not ready yet".to_string(),
+            ))
+        }
+    };
+
+    let disposition = format!(
+        "attachment; filename=\"blogverse-export-{}.json\"",
+        export_id
+    );
+
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_DISPOSITION, disposition)],
+        Json(archive),
+    ))
+}
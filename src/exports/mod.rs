@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{prelude::Type, PgExecutor};
+use uuid::Uuid;
+
+pub mod handler;
+pub mod worker;
+
+/// Progress of a data export
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "export_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Processing,
+    Ready,
+    Failed,
+}
+
+/// Database model for a data export
+#[derive(Debug, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct DataExport {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub status: ExportStatus,
+    pub archive: Option<Value>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// A data export as shown to its owner (without the archive itself)
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ExportResponse {
+    pub id: Uuid,
+    pub status: ExportStatus,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Collect everything stored about a user into one JSON document: profile, stories
+/// (with their JSONB content), comments, claps, follows and bookmarks
+pub async fn build_archive<'e>(
+    executor: impl PgExecutor<'e>,
+    user_id: Uuid,
+) -> Result<Value, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT jsonb_build_object(
+            'exported_at', NOW(),
+            'profile', (
+                SELECT jsonb_build_object(
+                    'id', u.id,
+                    'username', u.username,
+                    'email', u.email,
+                    'bio', u.bio,
+                    'image', u.image,
+                    'role', u.role,
+                    'email_verified', u.email_verified,
+                    'two_factor_enabled', u.totp_enabled_at IS NOT NULL,
+                    'followers_count', u.followers_count,
+                    'following_count', u.following_count,
+                    'created_at', u.created_at,
+                    'updated_at', u.updated_at
+                )
+                FROM users u WHERE u.id = $1
+            ),
+            'stories', COALESCE((
+                SELECT jsonb_agg(jsonb_build_object(
+                    'id', s.id,
+                    'title', s.title,
+                    'subtitle', s.subtitle,
+                    'slug', s.slug,
+                    'content', s.content,
+                    'status', s.status,
+                    'tags', COALESCE((
+                        SELECT jsonb_agg(t.name ORDER BY t.name)
+                        FROM story_tags st JOIN tags t ON st.tag_id = t.id
+                        WHERE st.story_id = s.id
+                    ), '[]'::jsonb),
+                    'clap_count', s.clap_count,
+                    'created_at', s.created_at,
+                    'updated_at', s.updated_at,
+                    'published_at', s.published_at,
+                    'scheduled_for', s.scheduled_for
+                ) ORDER BY s.created_at)
+                FROM stories s WHERE s.author_id = $1
+            ), '[]'::jsonb),
+            'comments', COALESCE((
+                SELECT jsonb_agg(jsonb_build_object(
+                    'id', c.id,
+                    'story_id', c.story_id,
+                    'parent_id', c.parent_id,
+                    'content', c.content,
+                    'clap_count', c.clap_count,
+                    'created_at', c.created_at,
+                    'updated_at', c.updated_at
+                ) ORDER BY c.created_at)
+                FROM comments c WHERE c.author_id = $1
+            ), '[]'::jsonb),
+            'claps', jsonb_build_object(
+                'stories', COALESCE((
+                    SELECT jsonb_agg(jsonb_build_object(
+                        'story_id', sc.story_id,
+                        'claps', sc.claps_count,
+                        'updated_at', sc.updated_at
+                    ) ORDER BY sc.updated_at)
+                    FROM story_claps sc WHERE sc.user_id = $1
+                ), '[]'::jsonb),
+                'comments', COALESCE((
+                    SELECT jsonb_agg(jsonb_build_object(
+                        'comment_id', cc.comment_id,
+                        'claps', cc.claps_count,
+                        'updated_at', cc.updated_at
+                    ) ORDER BY cc.updated_at)
+                    FROM comment_claps cc WHERE cc.user_id = $1
+                ), '[]'::jsonb)
+            ),
+            'follows', jsonb_build_object(
+                'following', COALESCE((
+                    SELECT jsonb_agg(jsonb_build_object(
+                        'user_id', u.id,
+                        'username', u.username,
+                        'since', f.created_at
+                    ) ORDER BY f.created_at)
+                    FROM follows f JOIN users u ON f.following_id = u.id
+                    WHERE f.follower_id = $1
+                ), '[]'::jsonb),
+                'followers', COALESCE((
+                    SELECT jsonb_agg(jsonb_build_object(
+                        'user_id', u.id,
+                        'username', u.username,
+                        'since', f.created_at
+                    ) ORDER BY f.created_at)
+                    FROM follows f JOIN users u ON f.follower_id = u.id
+                    WHERE f.following_id = $1
+                ), '[]'::jsonb)
+            ),
+            'bookmarks', COALESCE((
+                SELECT jsonb_agg(jsonb_build_object(
+                    'story_id', b.story_id,
+                    'title', s.title,
+                    'slug', s.slug,
+                    'created_at', b.created_at
+                ) ORDER BY b.created_at)
+                FROM bookmarks b JOIN stories s ON b.story_id = s.id
+                WHERE b.user_id = $1
+            ), '[]'::jsonb)
+        )
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(executor)
+    .await
+}
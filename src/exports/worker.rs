@@ -0,0 +1,176 @@
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{email::EmailService, exports::build_archive};
+
+/// Attempts at building an export before it is marked failed
+const MAX_ATTEMPTS: i32 = 3;
+
+/// Spawn the background task that builds pending data exports and emails the user
+/// when their archive is ready.
+///
+/// Pending exports are claimed with `FOR UPDATE SKIP LOCKED`, so several API
+/// instances can run this without building the same export twice. An export still
+/// processing after `timeout_secs` was abandoned by a worker that stopped, and is
+/// claimed again like a pending one.
+pub fn spawn(
+    pool: PgPool,
+    email_service: EmailService,
+    interval_secs: u64,
+    ttl_days: i64,
+    timeout_secs: i64,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = remove_expired_exports(&pool).await {
+                tracing::error!("Export cleanup error: {:?}", e);
+            }
+
+            match fail_abandoned_exports(&pool, ttl_days, timeout_secs).await {
+                Ok(0) => {}
+                Ok(failed) => tracing::warn!("Gave up on {} abandoned exports", failed),
+                Err(e) => tracing::error!("Export cleanup error: {:?}", e),
+            }
+
+            // Work through the whole queue before waiting for the next tick
+            loop {
+                match process_next_export(&pool, &email_service, ttl_days, timeout_secs).await {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(e) => {
+                        tracing::error!("Export worker error: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Build the oldest pending or abandoned export, if any. Returns whether one was claimed.
+async fn process_next_export(
+    pool: &PgPool,
+    email_service: &EmailService,
+    ttl_days: i64,
+    timeout_secs: i64,
+) -> Result<bool, sqlx::Error> {
+    let claimed: Option<(Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        UPDATE data_exports SET status = 'processing', started_at = NOW(), attempts = attempts + 1
+        WHERE id = (
+            SELECT id FROM data_exports
+            WHERE status = 'pending'
+                OR (status = 'processing' AND attempts < $2
+                    AND started_at <= NOW() - make_interval(secs => $1))
+            ORDER BY created_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, user_id
+        "#,
+    )
+    .bind(timeout_secs as f64)
+    .bind(MAX_ATTEMPTS)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some((export_id, user_id)) = claimed else {
+        return Ok(false);
+    };
+
+    let archive = match build_archive(pool, user_id).await {
+        Ok(archive) => archive,
+        Err(e) => {
+            tracing::error!("Failed to build export {}: {:?}", export_id, e);
+            sqlx::query(
+                r#"
+                UPDATE data_exports SET
+                    status = 'failed',
+                    completed_at = NOW(),
+                    expires_at = NOW() + make_interval(days => $2)
+                WHERE id = $1
+                "#,
+            )
+            .bind(export_id)
+            .bind(ttl_days as i32)
+            .execute(pool)
+            .await?;
+            return Ok(true);
+        }
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE data_exports SET
+            status = 'ready',
+            archive = $2,
+            completed_at = NOW(),
+            expires_at = NOW() + make_interval(days => $3)
+        WHERE id = $1
+        "#,
+    )
+    .bind(export_id)
+    .bind(&archive)
+    .bind(ttl_days as i32)
+    .execute(pool)
+    .await?;
+
+    tracing::info!("Data export {} ready", export_id);
+
+    let recipient: Option<(String, String)> =
+        sqlx::query_as("SELECT email, username FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    if let Some((email, username)) = recipient {
+        if let Err(e) = email_service
+            .send_export_ready_email(&email, &username, export_id)
+            .await
+        {
+            tracing::error!("Failed to send export ready email: {:?}", e);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Delete exports whose download window has passed (failed ones are kept just as long)
+async fn remove_expired_exports(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM data_exports WHERE expires_at <= NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Mark failed the exports that were abandoned on their last attempt, so the user can
+/// ask for a new one
+async fn fail_abandoned_exports(
+    pool: &PgPool,
+    ttl_days: i64,
+    timeout_secs: i64,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE data_exports SET
+            status = 'failed',
+            completed_at = NOW(),
+            expires_at = NOW() + make_interval(days => $3)
+        WHERE status = 'processing' AND attempts >= $2
+            AND started_at <= NOW() - make_interval(secs => $1)
+        "#,
+    )
+    .bind(timeout_secs as f64)
+    .bind(MAX_ATTEMPTS)
+    .bind(ttl_days as i32)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
@@ -4,19 +4,28 @@ use axum::{
     Json,
 };
 use serde::Deserialize;
-use sqlx::{FromRow, PgPool, Row};
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder, Row};
 use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::{
     auth::jwt,
+    blocks,
     error::AppError,
     follows::{
         FollowActionResponse, FollowListFilter, FollowListResponse, FollowUserResponse,
         UserProfileResponse,
     },
-    response::ApiResponse,
-    stories::{AuthorResponse, StoryResponse, StoryStatus},
+    mentions::MentionSpan,
+    notifications::{self, NotificationType},
+    response::{ApiResponse, Cursor, Pagination},
+    stories::{
+        handler::{
+            push_story_filters, push_story_keyset, push_viewer_exclusions, story_order_clause,
+            story_sort_time,
+        },
+        AuthorResponse, StoryResponse, StoryStatus,
+    },
 };
 
 /// Helper struct for fetching user with follow info
@@ -69,9 +78,15 @@ pub async fn follow_user(
         .map_err(|_| AppError::InternalServerError)?
         .ok_or(AppError::NotFound("User not found".to_string()))?;
 
+    if blocks::is_blocked(&pool, claims.sub, user_id).await? {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot follow this user".to_string(),
+        ));
+    }
+
     // Insert follow (ignore if already following)
     // The trigger will automatically update the followers_count
-    sqlx::query(
+    let inserted = sqlx::query(
         r#"
         INSERT INTO follows (follower_id, following_id)
         VALUES ($1, $2)
@@ -84,6 +99,19 @@ pub async fn follow_user(
     .await
     .map_err(|_| AppError::InternalServerError)?;
 
+    // Only notify on a new follow, not on repeated requests
+    if inserted.rows_affected() > 0 {
+        notifications::notify(
+            &pool,
+            user_id,
+            claims.sub,
+            NotificationType::Follow,
+            None,
+            None,
+        )
+        .await;
+    }
+
     // Get the denormalized follower count directly from users table
     let count_row = sqlx::query("SELECT followers_count FROM users WHERE id = $1")
         .bind(user_id)
@@ -155,38 +183,52 @@ pub async fn get_followers(
     let total: i32 = user_row.get("followers_count");
     let total = total as i64;
 
-    let limit = filter.limit.unwrap_or(20).min(100);
-    let offset = filter.offset.unwrap_or(0);
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        filter.offset.unwrap_or(0)
+    };
 
-    // Get followers with user info
-    let followers = sqlx::query_as::<_, UserFollowRow>(
+    // Get followers with user info (fetch one extra row to know whether there is a next page)
+    let mut followers = sqlx::query_as::<_, UserFollowRow>(
         r#"
         SELECT u.id, u.username, u.bio, u.image, f.created_at as followed_at
         FROM follows f
         JOIN users u ON f.follower_id = u.id
         WHERE f.following_id = $1
-        ORDER BY f.created_at DESC
+          AND ($4::TIMESTAMPTZ IS NULL OR (f.created_at, u.id) < ($4, $5))
+        ORDER BY f.created_at DESC, u.id DESC
         LIMIT $2 OFFSET $3
         "#,
     )
     .bind(user_id)
-    .bind(limit)
+    .bind(limit + 1)
     .bind(offset)
+    .bind(cursor.map(|c| c.timestamp))
+    .bind(cursor.map(|c| c.id))
     .fetch_all(&pool)
     .await
     .map_err(|_| AppError::InternalServerError)?;
 
+    let pagination =
+        Pagination::from_rows(&mut followers, limit, |u| Cursor::new(u.followed_at, u.id));
+
     let users: Vec<FollowUserResponse> = followers
         .into_iter()
         .map(FollowUserResponse::from)
         .collect();
-    let has_more = (offset + limit) < total;
 
-    Ok(ApiResponse::success(FollowListResponse {
-        users,
-        total,
-        has_more,
-    }))
+    Ok(ApiResponse::paginated(
+        FollowListResponse { users, total },
+        pagination,
+    ))
 }
 
 /// Get users that a user is following
@@ -207,38 +249,52 @@ pub async fn get_following(
     let total: i32 = user_row.get("following_count");
     let total = total as i64;
 
-    let limit = filter.limit.unwrap_or(20).min(100);
-    let offset = filter.offset.unwrap_or(0);
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        filter.offset.unwrap_or(0)
+    };
 
-    // Get following with user info
-    let following = sqlx::query_as::<_, UserFollowRow>(
+    // Get following with user info (fetch one extra row to know whether there is a next page)
+    let mut following = sqlx::query_as::<_, UserFollowRow>(
         r#"
         SELECT u.id, u.username, u.bio, u.image, f.created_at as followed_at
         FROM follows f
         JOIN users u ON f.following_id = u.id
         WHERE f.follower_id = $1
-        ORDER BY f.created_at DESC
+          AND ($4::TIMESTAMPTZ IS NULL OR (f.created_at, u.id) < ($4, $5))
+        ORDER BY f.created_at DESC, u.id DESC
         LIMIT $2 OFFSET $3
         "#,
     )
     .bind(user_id)
-    .bind(limit)
+    .bind(limit + 1)
     .bind(offset)
+    .bind(cursor.map(|c| c.timestamp))
+    .bind(cursor.map(|c| c.id))
     .fetch_all(&pool)
     .await
     .map_err(|_| AppError::InternalServerError)?;
 
+    let pagination =
+        Pagination::from_rows(&mut following, limit, |u| Cursor::new(u.followed_at, u.id));
+
     let users: Vec<FollowUserResponse> = following
         .into_iter()
         .map(FollowUserResponse::from)
         .collect();
-    let has_more = (offset + limit) < total;
 
-    Ok(ApiResponse::success(FollowListResponse {
-        users,
-        total,
-        has_more,
-    }))
+    Ok(ApiResponse::paginated(
+        FollowListResponse { users, total },
+        pagination,
+    ))
 }
 
 /// Get user profile with follow stats
@@ -301,11 +357,14 @@ struct StoryFromDb {
     clap_count: i32,
     created_at: chrono::DateTime<chrono::Utc>,
     published_at: Option<chrono::DateTime<chrono::Utc>>,
+    scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
     author_id: Uuid,
     username: String,
     bio: Option<String>,
     image: Option<String>,
     tags: Vec<String>,
+    mentions: sqlx::types::Json<Vec<MentionSpan>>,
+    is_bookmarked: bool,
 }
 
 impl From<StoryFromDb> for StoryResponse {
@@ -325,8 +384,11 @@ impl From<StoryFromDb> for StoryResponse {
             status: s.status,
             clap_count: s.clap_count,
             tags: s.tags,
+            mentions: s.mentions.0,
+            is_bookmarked: s.is_bookmarked,
             created_at: s.created_at,
             published_at: s.published_at,
+            scheduled_for: s.scheduled_for,
         }
     }
 }
@@ -338,38 +400,53 @@ pub async fn get_following_feed(
     claims: jwt::Claims,
     Query(filter): Query<crate::stories::StoryFilter>,
 ) -> Result<impl IntoResponse, AppError> {
-    let limit = filter.limit.unwrap_or(20).min(100);
-    let offset = filter.offset.unwrap_or(0);
-
-    let order_clause = match filter.sort.as_deref() {
-        Some("claps") => "s.clap_count DESC, s.created_at DESC",
-        _ => "s.created_at DESC",
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let by_claps = filter.sort.as_deref() == Some("claps");
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, by_claps))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        filter.offset.unwrap_or(0)
     };
 
-    let query_str = format!(
+    let mut qb = QueryBuilder::<Postgres>::new(
         r#"
         SELECT
             s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count,
-            s.created_at, s.updated_at, s.published_at, s.author_id,
+            s.created_at, s.updated_at, s.published_at, s.scheduled_for, s.author_id,
             u.username, u.bio, u.image,
-            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{{}}') as tags
+            story_mentions(s.id) as mentions,
+            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{}') as tags,
+            EXISTS(SELECT 1 FROM bookmarks b WHERE b.story_id = s.id AND b.user_id = f.follower_id) as is_bookmarked
         FROM stories s
         JOIN users u ON s.author_id = u.id
-        JOIN follows f ON s.author_id = f.following_id AND f.follower_id = $1
+        JOIN follows f ON s.author_id = f.following_id AND f.follower_id = "#,
+    );
+    qb.push_bind(claims.sub);
+    qb.push(
+        r#"
         LEFT JOIN story_tags st ON s.id = st.story_id
         LEFT JOIN tags t ON st.tag_id = t.id
-        WHERE s.status = 'published'
-        GROUP BY s.id, u.id
-        ORDER BY {}
-        LIMIT $2 OFFSET $3
-        "#,
-        order_clause
+        WHERE s.status = 'published' AND s.hidden_at IS NULL"#,
     );
-
-    let stories = sqlx::query_as::<_, StoryFromDb>(&query_str)
-        .bind(claims.sub)
-        .bind(limit)
-        .bind(offset)
+    push_viewer_exclusions(&mut qb, claims.sub);
+    push_story_filters(&mut qb, &filter)?;
+    push_story_keyset(&mut qb, cursor, by_claps);
+    qb.push(" GROUP BY s.id, u.id, f.follower_id ORDER BY ");
+    qb.push(story_order_clause(by_claps));
+    // Fetch one extra row to know whether there is a next page
+    qb.push(" LIMIT ");
+    qb.push_bind(limit + 1);
+    qb.push(" OFFSET ");
+    qb.push_bind(offset);
+
+    let mut stories = qb
+        .build_query_as::<StoryFromDb>()
         .fetch_all(&pool)
         .await
         .map_err(|e| {
@@ -377,9 +454,21 @@ pub async fn get_following_feed(
             AppError::InternalServerError
         })?;
 
+    let pagination = Pagination::from_rows(&mut stories, limit, |s| {
+        if by_claps {
+            Cursor::with_claps(
+                s.clap_count,
+                story_sort_time(s.published_at, s.created_at),
+                s.id,
+            )
+        } else {
+            Cursor::new(story_sort_time(s.published_at, s.created_at), s.id)
+        }
+    });
+
     let response: Vec<StoryResponse> = stories.into_iter().map(StoryResponse::from).collect();
 
-    Ok(ApiResponse::success(response))
+    Ok(ApiResponse::paginated(response, pagination))
 }
 
 /// Check if current user follows a target user
@@ -474,6 +563,15 @@ pub async fn get_follow_suggestions(
               SELECT 1 FROM follows
               WHERE follower_id = $1 AND following_id = f2.following_id
           )
+          AND NOT EXISTS (
+              SELECT 1 FROM user_mutes
+              WHERE muter_id = $1 AND muted_id = f2.following_id
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM user_blocks
+              WHERE (blocker_id = $1 AND blocked_id = f2.following_id)
+                 OR (blocker_id = f2.following_id AND blocked_id = $1)
+          )
         GROUP BY u.id
         ORDER BY mutual_count DESC, u.followers_count DESC
         LIMIT $2
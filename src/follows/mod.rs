@@ -1,10 +1,14 @@
 use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
 use uuid::Uuid;
 
+use crate::error::AppError;
+
 pub mod handler;
 
 /// Database model for a follow relationship
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[allow(dead_code)]
 pub struct Follow {
     pub follower_id: Uuid,
     pub following_id: Uuid,
@@ -25,7 +29,8 @@ pub struct FollowUserResponse {
 #[derive(Debug, Deserialize)]
 pub struct FollowListFilter {
     pub limit: Option<i64>,
-    pub offset: Option<i64>,
+    pub offset: Option<i64>, // Legacy, prefer cursor
+    pub cursor: Option<String>,
 }
 
 /// Response for paginated followers/following lists
@@ -33,7 +38,6 @@ pub struct FollowListFilter {
 pub struct FollowListResponse {
     pub users: Vec<FollowUserResponse>,
     pub total: i64,
-    pub has_more: bool,
 }
 
 /// User profile with follow stats
@@ -55,3 +59,19 @@ pub struct FollowActionResponse {
     pub following: bool,
     pub followers_count: i64,
 }
+
+/// Whether `follower_id` follows `following_id`
+pub async fn is_following<'e>(
+    executor: impl PgExecutor<'e>,
+    follower_id: Uuid,
+    following_id: Uuid,
+) -> Result<bool, AppError> {
+    sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM follows WHERE follower_id = $1 AND following_id = $2)",
+    )
+    .bind(follower_id)
+    .bind(following_id)
+    .fetch_one(executor)
+    .await
+    .map_err(|_| AppError::InternalServerError)
+}
@@ -1,29 +1,43 @@
 use axum::{
     extract::FromRef,
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use dotenv::dotenv;
 use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::net::SocketAddr;
 use tracing::info;
 
 mod auth;
+mod blocks;
+mod bookmarks;
 mod comments;
 mod config;
 mod email;
 mod error;
+mod exports;
 mod follows;
+mod mentions;
+mod moderation;
+mod notifications;
+mod rate_limit;
+mod reports;
 mod response;
+mod revisions;
+mod search;
 mod stories;
 
+use auth::oauth::OAuthClient;
 use config::settings::Settings;
 use email::EmailService;
+use rate_limit::RateLimiter;
 
 #[derive(Clone)]
 pub struct AppState {
     pool: PgPool,
     settings: Settings,
     email_service: EmailService,
+    oauth_client: OAuthClient,
 }
 
 impl FromRef<AppState> for PgPool {
@@ -44,6 +58,12 @@ impl FromRef<AppState> for EmailService {
     }
 }
 
+impl FromRef<AppState> for OAuthClient {
+    fn from_ref(app_state: &AppState) -> OAuthClient {
+        app_state.oauth_client.clone()
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
@@ -74,24 +94,133 @@ async fn main() -> anyhow::Result<()> {
     )?;
     info!("Email service initialized");
 
+    // Publish scheduled stories in the background
+    stories::publisher::spawn(
+        pool.clone(),
+        email_service.clone(),
+        settings.publish_interval_secs,
+    );
+    info!("Scheduled publisher started");
+
+    // Build requested data exports and remove accounts past their deletion grace period
+    exports::worker::spawn(
+        pool.clone(),
+        email_service.clone(),
+        settings.export_interval_secs,
+        settings.export_ttl_days,
+        settings.export_timeout_secs,
+    );
+    auth::deletion::spawn(pool.clone(), settings.purge_interval_secs);
+    info!("Export and account purge workers started");
+
     let app_state = AppState {
         pool,
         settings: settings.clone(),
         email_service,
+        oauth_client: OAuthClient::new()?,
     };
 
+    // Rate limiters, one token bucket set per route group
+    let auth_limiter = RateLimiter::per_ip(settings.rate_limit_auth);
+    let email_limiter = RateLimiter::per_ip(settings.rate_limit_email);
+    let clap_limiter = RateLimiter::per_user(settings.rate_limit_claps, &settings.jwt_secret);
+    let comment_limiter = RateLimiter::per_user(settings.rate_limit_comments, &settings.jwt_secret);
+
     // Auth routes
     let auth_router = Router::new()
-        .route("/sign-up", post(auth::handler::signup))
-        .route("/sign-in", post(auth::handler::login))
-        .route("/verify-email", post(auth::handler::verify_email))
+        .route(
+            "/sign-up",
+            post(auth::handler::signup).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/sign-in",
+            post(auth::handler::login).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/sign-in/2fa",
+            post(auth::handler::verify_two_factor).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/magic-link",
+            post(auth::handler::request_magic_link).route_layer(email_limiter.clone()),
+        )
+        .route(
+            "/magic-link/verify",
+            post(auth::handler::verify_magic_link).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/verify-email",
+            post(auth::handler::verify_email).route_layer(auth_limiter.clone()),
+        )
         .route(
             "/resend-verification",
-            post(auth::handler::resend_verification),
+            post(auth::handler::resend_verification).route_layer(email_limiter.clone()),
+        )
+        .route(
+            "/forgot-password",
+            post(auth::handler::forgot_password).route_layer(email_limiter.clone()),
+        )
+        .route(
+            "/reset-password",
+            post(auth::handler::reset_password).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/change-password",
+            post(auth::handler::change_password).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/change-email",
+            post(auth::handler::change_email).route_layer(email_limiter.clone()),
+        )
+        .route(
+            "/confirm-email-change",
+            post(auth::handler::confirm_email_change).route_layer(auth_limiter.clone()),
+        )
+        .route("/oauth/providers", get(auth::handler::get_oauth_providers))
+        .route(
+            "/oauth/{provider}/authorize",
+            get(auth::handler::oauth_authorize).route_layer(auth_limiter.clone()),
+        )
+        .route(
+            "/oauth/{provider}/callback",
+            post(auth::handler::oauth_callback).route_layer(auth_limiter.clone()),
+        )
+        .route("/refresh", post(auth::handler::refresh))
+        .route("/logout", post(auth::handler::logout))
+        .route(
+            "/sessions",
+            get(auth::handler::get_sessions).delete(auth::handler::revoke_other_sessions),
+        )
+        .route("/sessions/{id}", delete(auth::handler::revoke_session))
+        .route("/me/security-log", get(auth::handler::get_security_log))
+        .route("/2fa/enroll", post(auth::handler::enroll_two_factor))
+        .route("/2fa/confirm", post(auth::handler::confirm_two_factor))
+        .route(
+            "/2fa/recovery-codes",
+            post(auth::handler::regenerate_recovery_codes),
+        )
+        .route("/2fa/disable", post(auth::handler::disable_two_factor))
+        .route(
+            "/me",
+            get(auth::handler::get_me)
+                .put(auth::handler::update_me)
+                .delete(auth::handler::delete_me),
+        )
+        .route("/me/identities", get(auth::handler::get_identities))
+        .route(
+            "/me/identities/{provider}",
+            post(auth::handler::link_identity).delete(auth::handler::unlink_identity),
+        )
+        .route(
+            "/me/identities/{provider}/callback",
+            post(auth::handler::link_identity_callback).route_layer(auth_limiter.clone()),
         )
-        .route("/forgot-password", post(auth::handler::forgot_password))
-        .route("/reset-password", post(auth::handler::reset_password))
-        .route("/me", get(auth::handler::get_me));
+        .route("/me/export", post(exports::handler::request_export))
+        .route("/me/exports/{id}", get(exports::handler::get_export))
+        .route(
+            "/me/exports/{id}/download",
+            get(exports::handler::download_export),
+        );
 
     // User routes (with follow operations)
     // More specific routes must come before generic /{id}
@@ -105,8 +234,19 @@ async fn main() -> anyhow::Result<()> {
             "/suggestions",
             get(follows::handler::get_follow_suggestions),
         )
+        .route("/me/bookmarks", get(bookmarks::handler::get_bookmarks))
+        .route("/me/blocks", get(blocks::handler::get_blocked_users))
+        .route("/me/mutes", get(blocks::handler::get_muted_users))
         // User-specific routes
         .route("/{id}/profile", get(follows::handler::get_user_profile))
+        .route(
+            "/{id}/block",
+            post(blocks::handler::block_user).delete(blocks::handler::unblock_user),
+        )
+        .route(
+            "/{id}/mute",
+            post(blocks::handler::mute_user).delete(blocks::handler::unmute_user),
+        )
         .route(
             "/{id}/follow",
             post(follows::handler::follow_user).delete(follows::handler::unfollow_user),
@@ -124,11 +264,43 @@ async fn main() -> anyhow::Result<()> {
             post(stories::handler::create_story).get(stories::handler::get_feed),
         )
         .route("/s/{slug}", get(stories::handler::get_story))
+        .route("/me", get(stories::handler::get_my_stories))
         // More specific routes must come before /{id}
-        .route("/{id}/clap", post(stories::handler::clap_story))
+        .route(
+            "/{id}/clap",
+            post(stories::handler::clap_story).route_layer(clap_limiter.clone()),
+        )
+        .route("/{id}/revisions", get(revisions::handler::get_revisions))
+        .route(
+            "/{id}/revisions/diff",
+            get(revisions::handler::diff_revisions),
+        )
+        .route(
+            "/{id}/revisions/{revision}",
+            get(revisions::handler::get_revision),
+        )
+        .route(
+            "/{id}/revisions/{revision}/restore",
+            post(revisions::handler::restore_revision),
+        )
+        .route(
+            "/{id}/bookmark",
+            post(bookmarks::handler::add_bookmark).delete(bookmarks::handler::remove_bookmark),
+        )
         .route(
             "/{id}/comments",
-            post(comments::handler::create_comment).get(comments::handler::get_story_comments),
+            post(comments::handler::create_comment)
+                .route_layer(comment_limiter.clone())
+                .get(comments::handler::get_story_comments),
+        )
+        .route(
+            "/{id}/comments/tree",
+            get(comments::handler::get_comment_tree),
+        )
+        .route(
+            "/{id}/comment-settings",
+            get(comments::handler::get_comment_settings)
+                .put(comments::handler::update_comment_settings),
         )
         // Generic /{id} route comes last
         .route(
@@ -149,7 +321,63 @@ async fn main() -> anyhow::Result<()> {
                 .delete(comments::handler::delete_comment),
         )
         .route("/{id}/replies", get(comments::handler::get_comment_replies))
-        .route("/{id}/clap", post(comments::handler::clap_comment));
+        .route("/{id}/history", get(comments::handler::get_comment_history))
+        .route(
+            "/{id}/pin",
+            post(comments::handler::pin_comment).delete(comments::handler::unpin_comment),
+        )
+        .route(
+            "/{id}/visibility",
+            axum::routing::put(comments::handler::set_comment_hidden),
+        )
+        .route(
+            "/{id}/clap",
+            post(comments::handler::clap_comment).route_layer(clap_limiter.clone()),
+        );
+
+    // Notification routes
+    let notification_router = Router::new()
+        .route("/", get(notifications::handler::get_notifications))
+        .route(
+            "/unread-count",
+            get(notifications::handler::get_unread_count),
+        )
+        .route("/read-all", post(notifications::handler::mark_all_read))
+        .route(
+            "/preferences",
+            get(notifications::handler::get_preferences)
+                .put(notifications::handler::update_preferences),
+        )
+        .route("/{id}/read", post(notifications::handler::mark_read));
+
+    // Search routes
+    let search_router = Router::new().route("/", get(search::handler::search));
+
+    // Moderation routes (moderators and admins)
+    let moderation_router = Router::new()
+        .route("/log", get(moderation::handler::get_moderation_log))
+        .route("/reports", get(reports::handler::get_reports))
+        .route(
+            "/reports/{id}/resolve",
+            post(reports::handler::resolve_report),
+        )
+        .route(
+            "/stories/{id}/visibility",
+            axum::routing::put(moderation::handler::set_story_visibility),
+        )
+        .route(
+            "/comments/{id}/visibility",
+            axum::routing::put(moderation::handler::set_comment_visibility),
+        );
+
+    // Report routes
+    let report_router = Router::new().route("/", post(reports::handler::create_report));
+
+    // Admin routes
+    let admin_router = Router::new().route(
+        "/users/{id}/role",
+        axum::routing::put(moderation::handler::update_user_role),
+    );
 
     // Feed routes (personalized feed)
     let feed_router = Router::new().route("/following", get(follows::handler::get_following_feed));
@@ -164,12 +392,22 @@ async fn main() -> anyhow::Result<()> {
         .nest("/api/tags", tag_router)
         .nest("/api/comments", comment_router)
         .nest("/api/feed", feed_router)
+        .nest("/api/search", search_router)
+        .nest("/api/notifications", notification_router)
+        .nest("/api/reports", report_router)
+        .nest("/api/moderation", moderation_router)
+        .nest("/api/admin", admin_router)
         .with_state(app_state);
 
     info!("Server running on http://localhost:{}", settings.port);
 
     let listener = tokio::net::TcpListener::bind(settings.addr).await?;
-    axum::serve(listener, app).await?;
+    // Connect info is needed for the client IP recorded on sessions
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
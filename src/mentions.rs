@@ -0,0 +1,491 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::{
+    blocks,
+    email::EmailService,
+    error::AppError,
+    notifications::{self, NotificationType},
+};
+
+/// Longest username the users table allows
+const MAX_USERNAME_LEN: usize = 50;
+/// Most users one story or comment can mention; later names stay plain text
+const MAX_MENTIONED_USERS: usize = 20;
+
+/// Where a user is mentioned, for clients to turn into a link. `start` and `end` are
+/// character offsets into the text, covering the `@`. In stories, `path` holds the
+/// child indexes leading from the content root to the text node.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MentionSpan {
+    pub user_id: Uuid,
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<Vec<usize>>,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// What a mention is in
+#[derive(Debug, Clone, Copy)]
+pub enum MentionTarget {
+    Story(Uuid),
+    Comment(Uuid),
+}
+
+impl MentionTarget {
+    fn column(self) -> &'static str {
+        match self {
+            MentionTarget::Story(_) => "story_id",
+            MentionTarget::Comment(_) => "comment_id",
+        }
+    }
+
+    fn id(self) -> Uuid {
+        match self {
+            MentionTarget::Story(id) | MentionTarget::Comment(id) => id,
+        }
+    }
+}
+
+/// A mention as written, before it is resolved to a user
+struct FoundMention {
+    path: Option<Vec<usize>>,
+    start: usize,
+    end: usize,
+    username: String,
+}
+
+/// Find the `@username` mentions in a piece of text as (start, end, username), with
+/// character offsets. An `@` right after a letter or digit, as in an email address,
+/// doesn't start a mention.
+pub fn parse(text: &str) -> Vec<(usize, usize, String)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut found = Vec::new();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let after_word = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        if chars[i] != '@' || after_word {
+            i += 1;
+            continue;
+        }
+
+        let mut end = i + 1;
+        while end < chars.len() && is_username_char(chars[end]) {
+            end += 1;
+        }
+        // Punctuation ending a sentence isn't part of the name
+        while end > i + 1 && matches!(chars[end - 1], '.' | '-') {
+            end -= 1;
+        }
+
+        let len = end - i - 1;
+        if (1..=MAX_USERNAME_LEN).contains(&len) {
+            found.push((i, end, chars[i + 1..end].iter().collect()));
+        }
+        i = end.max(i + 1);
+    }
+
+    found
+}
+
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Whether `parse` would find the whole of `username` after an `@`: ASCII letters,
+/// digits, `_`, `-` and `.`, not ending in `.` or `-` and not starting with them
+/// either. Usernames are held to this (also by a check on the users table).
+pub fn is_mentionable(username: &str) -> bool {
+    let edge = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    username.chars().all(is_username_char)
+        && username.chars().next().is_some_and(edge)
+        && username.chars().last().is_some_and(edge)
+}
+
+/// Mentions in story content: every "text" node of rich-text (TipTap/ProseMirror)
+/// content, or the whole string for plain Markdown/HTML content
+fn find_in_story(content: &Value) -> Vec<FoundMention> {
+    fn walk(node: &Value, path: &mut Vec<usize>, found: &mut Vec<FoundMention>) {
+        if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+            found.extend(
+                parse(text)
+                    .into_iter()
+                    .map(|(start, end, username)| FoundMention {
+                        path: Some(path.clone()),
+                        start,
+                        end,
+                        username,
+                    }),
+            );
+        }
+
+        if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+            for (index, child) in children.iter().enumerate() {
+                path.push(index);
+                walk(child, path, found);
+                path.pop();
+            }
+        }
+    }
+
+    let mut found = Vec::new();
+    match content.as_str() {
+        Some(text) => {
+            found.extend(
+                parse(text)
+                    .into_iter()
+                    .map(|(start, end, username)| FoundMention {
+                        path: Some(Vec::new()),
+                        start,
+                        end,
+                        username,
+                    }),
+            )
+        }
+        None => walk(content, &mut Vec::new(), &mut found),
+    }
+    found
+}
+
+fn find_in_comment(content: &str) -> Vec<FoundMention> {
+    parse(content)
+        .into_iter()
+        .map(|(start, end, username)| FoundMention {
+            path: None,
+            start,
+            end,
+            username,
+        })
+        .collect()
+}
+
+/// Record the mentions in a story's content, replacing those of its previous version
+pub async fn sync_story(
+    conn: &mut PgConnection,
+    story_id: Uuid,
+    content: &Value,
+) -> Result<(), AppError> {
+    sync(conn, MentionTarget::Story(story_id), find_in_story(content)).await
+}
+
+/// Record the mentions in a comment, replacing those of its previous version
+pub async fn sync_comment(
+    conn: &mut PgConnection,
+    comment_id: Uuid,
+    content: &str,
+) -> Result<(), AppError> {
+    sync(
+        conn,
+        MentionTarget::Comment(comment_id),
+        find_in_comment(content),
+    )
+    .await
+}
+
+/// Resolve mentions against usernames and store them, one row per mentioned user.
+/// Names that don't belong to anyone are left as plain text, as are users past the
+/// first `MAX_MENTIONED_USERS`. Users who were already mentioned keep their row, so
+/// they aren't notified again.
+async fn sync(
+    conn: &mut PgConnection,
+    target: MentionTarget,
+    found: Vec<FoundMention>,
+) -> Result<(), AppError> {
+    let mut usernames: Vec<&str> = found.iter().map(|m| m.username.as_str()).collect();
+    usernames.sort_unstable();
+    usernames.dedup();
+    let users: HashMap<String, Uuid> = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT id, username FROM users WHERE username = ANY($1)",
+    )
+    .bind(&usernames)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .into_iter()
+    .map(|(id, username)| (username, id))
+    .collect();
+
+    let mut spans: HashMap<Uuid, Vec<Value>> = HashMap::new();
+    for mention in found {
+        let Some(&user_id) = users.get(&mention.username) else {
+            continue;
+        };
+        if !spans.contains_key(&user_id) && spans.len() >= MAX_MENTIONED_USERS {
+            continue;
+        }
+        let mut span = json!({ "start": mention.start, "end": mention.end });
+        if let Some(path) = mention.path {
+            span["path"] = json!(path);
+        }
+        spans.entry(user_id).or_default().push(span);
+    }
+
+    let column = target.column();
+    let user_ids: Vec<Uuid> = spans.keys().copied().collect();
+
+    sqlx::query(&format!(
+        "DELETE FROM mentions WHERE {} = $1 AND user_id <> ALL($2)",
+        column
+    ))
+    .bind(target.id())
+    .bind(&user_ids)
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    for (user_id, spans) in spans {
+        sqlx::query(&format!(
+            r#"
+            INSERT INTO mentions (user_id, {column}, spans) VALUES ($1, $2, $3)
+            ON CONFLICT ({column}, user_id) WHERE {column} IS NOT NULL
+            DO UPDATE SET spans = EXCLUDED.spans
+            "#,
+            column = column
+        ))
+        .bind(user_id)
+        .bind(target.id())
+        .bind(Value::Array(spans))
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record mention: {:?}", e);
+            AppError::InternalServerError
+        })?;
+    }
+
+    Ok(())
+}
+
+/// A mention claimed for notifying, with what the notification needs
+#[derive(FromRow)]
+struct PendingMention {
+    user_id: Uuid,
+    email: String,
+    username: String,
+    email_verified: bool,
+    mention_in_app: bool,
+    mention_email: bool,
+    actor_id: Uuid,
+    actor_username: String,
+    story_id: Uuid,
+    story_title: String,
+    story_slug: String,
+    comment_id: Option<Uuid>,
+}
+
+/// Tell users about mentions they haven't heard about yet, once the story or comment
+/// is public: stories when they are published, comments unless hidden or deleted.
+/// Each user gets an in-app notification and an email, as their notification
+/// preferences allow. Emails are sent in the background so the request doesn't wait
+/// on SMTP. Failures are only logged, like other notifications.
+pub async fn notify(pool: &PgPool, email_service: &EmailService, target: MentionTarget) {
+    // Claiming the rows first means overlapping edits can't notify anyone twice
+    let claimed = match target {
+        MentionTarget::Story(_) => {
+            r#"
+            UPDATE mentions m SET notified_at = NOW()
+            FROM stories s
+            WHERE m.story_id = $1 AND m.notified_at IS NULL
+                AND s.id = m.story_id AND s.status = 'published' AND s.hidden_at IS NULL
+            RETURNING m.user_id, s.author_id as actor_id, s.id as story_id,
+                NULL::uuid as comment_id, s.title as story_title, s.slug as story_slug
+            "#
+        }
+        MentionTarget::Comment(_) => {
+            r#"
+            UPDATE mentions m SET notified_at = NOW()
+            FROM comments c
+            JOIN stories s ON c.story_id = s.id
+            WHERE m.comment_id = $1 AND m.notified_at IS NULL
+                AND c.id = m.comment_id AND c.hidden_at IS NULL AND c.deleted_at IS NULL
+            RETURNING m.user_id, c.author_id as actor_id, s.id as story_id,
+                c.id as comment_id, s.title as story_title, s.slug as story_slug
+            "#
+        }
+    };
+
+    let pending = sqlx::query_as::<_, PendingMention>(&format!(
+        r#"
+        WITH claimed AS ({})
+        SELECT
+            claimed.*, u.email, u.username, u.email_verified, actor.username as actor_username,
+            COALESCE(p.mention_in_app, TRUE) as mention_in_app,
+            COALESCE(p.mention_email, TRUE) as mention_email
+        FROM claimed
+        JOIN users u ON claimed.user_id = u.id
+        JOIN users actor ON claimed.actor_id = actor.id
+        LEFT JOIN notification_preferences p ON claimed.user_id = p.user_id
+        "#,
+        claimed
+    ))
+    .bind(target.id())
+    .fetch_all(pool)
+    .await;
+
+    let pending = match pending {
+        Ok(pending) => pending,
+        Err(e) => {
+            tracing::error!("Failed to claim mentions: {:?}", e);
+            return;
+        }
+    };
+
+    let mut emails = Vec::new();
+    for mention in pending {
+        if mention.user_id == mention.actor_id {
+            continue;
+        }
+
+        // Blocks apply in both directions; if we can't tell, stay quiet
+        if blocks::is_blocked(pool, mention.user_id, mention.actor_id)
+            .await
+            .unwrap_or(true)
+        {
+            continue;
+        }
+
+        if mention.mention_in_app {
+            notifications::notify(
+                pool,
+                mention.user_id,
+                mention.actor_id,
+                NotificationType::Mention,
+                Some(mention.story_id),
+                mention.comment_id,
+            )
+            .await;
+        }
+
+        if mention.mention_email && mention.email_verified {
+            emails.push(mention);
+        }
+    }
+
+    if emails.is_empty() {
+        return;
+    }
+
+    let email_service = email_service.clone();
+    tokio::spawn(async move {
+        for mention in emails {
+            if let Err(e) = email_service
+                .send_mention_email(
+                    &mention.email,
+                    &mention.username,
+                    &mention.actor_username,
+                    &mention.story_title,
+                    &mention.story_slug,
+                    mention.comment_id,
+                )
+                .await
+            {
+                tracing::error!("Failed to send mention email: {:?}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(text: &str) -> Vec<String> {
+        parse(text).into_iter().map(|(_, _, name)| name).collect()
+    }
+
+    #[test]
+    fn parse_finds_mentions_with_offsets() {
+        assert_eq!(
+            parse("hi @alice and @bob_2"),
+            vec![(3, 9, "alice".to_string()), (14, 20, "bob_2".to_string())]
+        );
+        assert_eq!(names("@alice"), vec!["alice"]);
+        assert_eq!(names("(@alice)"), vec!["alice"]);
+        assert_eq!(names("@jane.doe-smith"), vec!["jane.doe-smith"]);
+    }
+
+    #[test]
+    fn parse_skips_email_addresses() {
+        assert!(parse("write to alice@example.com").is_empty());
+        assert!(parse("user_@host").is_empty());
+        assert_eq!(names("mail me@home or @bob"), vec!["bob"]);
+    }
+
+    #[test]
+    fn parse_drops_trailing_punctuation() {
+        assert_eq!(names("thanks @alice."), vec!["alice"]);
+        assert_eq!(names("@alice, @bob!"), vec!["alice", "bob"]);
+        assert_eq!(names("ask @alice... or @bob-"), vec!["alice", "bob"]);
+        assert_eq!(names("@alice's post"), vec!["alice"]);
+    }
+
+    #[test]
+    fn parse_ignores_bare_and_overlong_names() {
+        assert!(parse("@ @. @- @@").is_empty());
+        assert!(parse(&format!("@{}", "a".repeat(MAX_USERNAME_LEN + 1))).is_empty());
+        assert_eq!(
+            names(&format!("@{}", "a".repeat(MAX_USERNAME_LEN))).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn parse_counts_characters_not_bytes() {
+        // "é" and the emoji take several bytes but one character each
+        assert_eq!(parse("café @alice"), vec![(5, 11, "alice".to_string())]);
+        assert_eq!(parse("🎉 @bob"), vec![(2, 6, "bob".to_string())]);
+    }
+
+    #[test]
+    fn parse_stops_at_non_ascii_letters() {
+        assert_eq!(names("@zoë"), vec!["zo"]);
+        assert!(parse("é@alice").is_empty());
+        assert!(parse("@ünïcode").is_empty());
+    }
+
+    #[test]
+    fn mentionable_usernames_are_parsed_whole() {
+        for username in ["alice", "bob_2", "jane.doe", "a-b", "_x_", "A1"] {
+            assert!(is_mentionable(username), "{}", username);
+            assert_eq!(names(&format!("hey @{}.", username)), vec![username]);
+        }
+        for username in ["", "zoë", "alice.", "-bob", ".x", "has space", "a@b"] {
+            assert!(!is_mentionable(username), "{}", username);
+        }
+    }
+
+    #[test]
+    fn story_mentions_carry_the_text_node_path() {
+        let content = json!({
+            "type": "doc",
+            "content": [
+                { "type": "paragraph", "content": [{ "type": "text", "text": "hi @alice" }] },
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "plain " },
+                    { "type": "text", "text": "@bob" }
+                ] }
+            ]
+        });
+
+        let found: Vec<_> = find_in_story(&content)
+            .into_iter()
+            .map(|m| (m.path.unwrap(), m.start, m.end, m.username))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (vec![0, 0], 3, 9, "alice".to_string()),
+                (vec![1, 1], 0, 4, "bob".to_string()),
+            ]
+        );
+
+        let found = find_in_story(&json!("markdown @carol"));
+        assert_eq!(found[0].path, Some(Vec::new()));
+        assert_eq!(found[0].username, "carol");
+    }
+}
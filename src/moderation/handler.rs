@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    auth::{
+        roles::{Admin, Moderator, RequireRole},
+        Role,
+    },
+    error::AppError,
+    moderation::{
+        self, ModerationAction, ModerationLogEntry, ModerationLogFilter, RoleResponse,
+        UpdateRoleRequest, VisibilityRequest, VisibilityResponse,
+    },
+    reports::ReportTarget,
+    response::{ApiResponse, Cursor, Pagination},
+};
+
+/// Get the moderation audit log, newest first
+/// GET /api/moderation/log
+pub async fn get_moderation_log(
+    State(pool): State<PgPool>,
+    _moderator: RequireRole<Moderator>,
+    Query(filter): Query<ModerationLogFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+
+    // Fetch one extra row to know whether there is a next page
+    let mut entries = sqlx::query_as::<_, ModerationLogEntry>(
+        r#"
+        SELECT
+            l.id, l.moderator_id, u.username as moderator_username, l.action,
+            l.target_id, l.target_user_id, l.reason, l.created_at
+        FROM moderation_log l
+        LEFT JOIN users u ON l.moderator_id = u.id
+        WHERE ($2::TIMESTAMPTZ IS NULL OR (l.created_at, l.id) < ($2, $3))
+        ORDER BY l.created_at DESC, l.id DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(limit + 1)
+    .bind(cursor.as_ref().map(|c| c.timestamp))
+    .bind(cursor.as_ref().map(|c| c.id))
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch moderation log: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let pagination =
+        Pagination::from_rows(&mut entries, limit, |e| Cursor::new(e.created_at, e.id));
+
+    Ok(ApiResponse::paginated(entries, pagination))
+}
+
+/// Change a user's role
+/// PUT /api/admin/users/:id/role
+pub async fn update_user_role(
+    State(pool): State<PgPool>,
+    admin: RequireRole<Admin>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<UpdateRoleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let reason = moderation::require_reason(payload.reason)?;
+
+    // Keeps the last admin from locking everyone out by demoting themselves
+    if user_id == admin.claims.sub {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot change your own role".to_string(),
+        ));
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let role: Role = sqlx::query_scalar(
+        "UPDATE users SET role = $1, updated_at = NOW() WHERE id = $2 RETURNING role",
+    )
+    .bind(payload.role)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    moderation::log_action(
+        &mut *tx,
+        admin.claims.sub,
+        ModerationAction::ChangeRole,
+        user_id,
+        Some(user_id),
+        &reason,
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(RoleResponse { user_id, role }))
+}
+
+async fn set_visibility(
+    pool: &PgPool,
+    moderator_id: Uuid,
+    target_type: ReportTarget,
+    target_id: Uuid,
+    payload: VisibilityRequest,
+) -> Result<ApiResponse<VisibilityResponse>, AppError> {
+    let reason = moderation::require_reason(payload.reason)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    moderation::set_hidden(
+        &mut tx,
+        moderator_id,
+        target_type,
+        target_id,
+        payload.hidden,
+        &reason,
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(VisibilityResponse {
+        id: target_id,
+        hidden: payload.hidden,
+    }))
+}
+
+/// Hide or unhide a story
+/// PUT /api/moderation/stories/:id/visibility
+pub async fn set_story_visibility(
+    State(pool): State<PgPool>,
+    moderator: RequireRole<Moderator>,
+    Path(story_id): Path<Uuid>,
+    Json(payload): Json<VisibilityRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    set_visibility(
+        &pool,
+        moderator.claims.sub,
+        ReportTarget::Story,
+        story_id,
+        payload,
+    )
+    .await
+}
+
+/// Hide or unhide a comment
+/// PUT /api/moderation/comments/:id/visibility
+pub async fn set_comment_visibility(
+    State(pool): State<PgPool>,
+    moderator: RequireRole<Moderator>,
+    Path(comment_id): Path<Uuid>,
+    Json(payload): Json<VisibilityRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    set_visibility(
+        &pool,
+        moderator.claims.sub,
+        ReportTarget::Comment,
+        comment_id,
+        payload,
+    )
+    .await
+}
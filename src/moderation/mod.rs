@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::Type, PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::{auth::Role, error::AppError, reports::ReportTarget};
+
+pub mod handler;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "moderation_action", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    DeleteStory,
+    DeleteComment,
+    ChangeRole,
+    HideStory,
+    UnhideStory,
+    HideComment,
+    UnhideComment,
+    ResolveReport,
+}
+
+/// Audit reason moderators must give when acting on someone else's content
+#[derive(Debug, Deserialize)]
+pub struct ModerationReason {
+    pub reason: Option<String>,
+}
+
+/// Request payload for changing a user's role
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleRequest {
+    pub role: Role,
+    pub reason: Option<String>,
+}
+
+/// Request payload for hiding or unhiding a story or comment
+#[derive(Debug, Deserialize)]
+pub struct VisibilityRequest {
+    pub hidden: bool,
+    pub reason: Option<String>,
+}
+
+/// Response for a visibility change
+#[derive(Debug, Serialize)]
+pub struct VisibilityResponse {
+    pub id: Uuid,
+    pub hidden: bool,
+}
+
+/// Query parameters for the moderation log
+#[derive(Debug, Deserialize)]
+pub struct ModerationLogFilter {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Response structure for a moderation log entry
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ModerationLogEntry {
+    pub id: Uuid,
+    pub moderator_id: Option<Uuid>,
+    pub moderator_username: Option<String>,
+    pub action: ModerationAction,
+    pub target_id: Uuid,
+    pub target_user_id: Option<Uuid>,
+    pub reason: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for a role change
+#[derive(Debug, Serialize)]
+pub struct RoleResponse {
+    pub user_id: Uuid,
+    pub role: Role,
+}
+
+/// Check the audit reason given for a moderator action
+pub fn require_reason(reason: Option<String>) -> Result<String, AppError> {
+    let reason = reason.map(|r| r.trim().to_string()).unwrap_or_default();
+
+    if reason.is_empty() {
+        return Err(AppError::UnprocessableEntity(
+            "A reason is required for moderation actions".to_string(),
+        ));
+    }
+
+    if reason.chars().count() > 500 {
+        return Err(AppError::UnprocessableEntity(
+            "Reason must be at most 500 characters".to_string(),
+        ));
+    }
+
+    Ok(reason)
+}
+
+/// Record a moderator action in the audit log.
+/// Run it in the same transaction as the action so neither happens without the other.
+pub async fn log_action<'e>(
+    executor: impl PgExecutor<'e>,
+    moderator_id: Uuid,
+    action: ModerationAction,
+    target_id: Uuid,
+    target_user_id: Option<Uuid>,
+    reason: &str,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO moderation_log (moderator_id, action, target_id, target_user_id, reason)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(moderator_id)
+    .bind(action)
+    .bind(target_id)
+    .bind(target_user_id)
+    .bind(reason)
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to record moderation action: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(())
+}
+
+/// Hide or unhide a story or comment and record it in the audit log.
+/// Hidden content stays in the database but drops out of feeds, comment lists and story pages.
+pub async fn set_hidden(
+    conn: &mut PgConnection,
+    moderator_id: Uuid,
+    target_type: ReportTarget,
+    target_id: Uuid,
+    hidden: bool,
+    reason: &str,
+) -> Result<(), AppError> {
+    let (query, action) = match (target_type, hidden) {
+        (ReportTarget::Story, true) => (
+            "UPDATE stories SET hidden_at = COALESCE(hidden_at, NOW()) WHERE id = $1 RETURNING author_id",
+            ModerationAction::HideStory,
+        ),
+        (ReportTarget::Story, false) => (
+            "UPDATE stories SET hidden_at = NULL WHERE id = $1 RETURNING author_id",
+            ModerationAction::UnhideStory,
+        ),
+        (ReportTarget::Comment, true) => (
+            "UPDATE comments SET hidden_at = COALESCE(hidden_at, NOW()), hidden_by_author = FALSE WHERE id = $1 RETURNING author_id",
+            ModerationAction::HideComment,
+        ),
+        (ReportTarget::Comment, false) => (
+            "UPDATE comments SET hidden_at = NULL, hidden_by_author = FALSE WHERE id = $1 RETURNING author_id",
+            ModerationAction::UnhideComment,
+        ),
+        (ReportTarget::User, _) => {
+            return Err(AppError::UnprocessableEntity(
+                "Only stories and comments can be hidden".to_string(),
+            ))
+        }
+    };
+
+    // Tombstoned comments lose their author when the account is deleted
+    let author_id: Option<Uuid> = sqlx::query_scalar(query)
+        .bind(target_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("Content not found".to_string()))?;
+
+    log_action(
+        &mut *conn,
+        moderator_id,
+        action,
+        target_id,
+        author_id,
+        reason,
+    )
+    .await
+}
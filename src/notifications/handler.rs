@@ -0,0 +1,233 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    auth::jwt,
+    error::AppError,
+    notifications::{
+        NotificationActor, NotificationFilter, NotificationListResponse, NotificationPreferences,
+        NotificationResponse, NotificationStory, NotificationType, UnreadCountResponse,
+        UpdateNotificationPreferences,
+    },
+    response::{ApiResponse, Cursor, Pagination},
+};
+
+/// Helper struct for fetching notifications with actor and story info
+#[derive(FromRow)]
+struct NotificationFromDb {
+    id: Uuid,
+    notification_type: NotificationType,
+    story_id: Option<Uuid>,
+    comment_id: Option<Uuid>,
+    event_count: i32,
+    read_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    // Actor fields
+    actor_id: Uuid,
+    actor_username: String,
+    actor_image: Option<String>,
+    // Story fields
+    story_title: Option<String>,
+    story_slug: Option<String>,
+}
+
+impl From<NotificationFromDb> for NotificationResponse {
+    fn from(n: NotificationFromDb) -> Self {
+        let story = match (n.story_id, n.story_title, n.story_slug) {
+            (Some(id), Some(title), Some(slug)) => Some(NotificationStory { id, title, slug }),
+            _ => None,
+        };
+
+        NotificationResponse {
+            id: n.id,
+            notification_type: n.notification_type,
+            actor: NotificationActor {
+                id: n.actor_id,
+                username: n.actor_username,
+                image: n.actor_image,
+            },
+            story,
+            comment_id: n.comment_id,
+            event_count: n.event_count,
+            is_read: n.read_at.is_some(),
+            created_at: n.created_at,
+            updated_at: n.updated_at,
+        }
+    }
+}
+
+async fn count_unread(pool: &PgPool, user_id: Uuid) -> Result<i64, AppError> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)
+}
+
+/// Get the current user's notifications, most recent activity first
+/// GET /api/notifications
+pub async fn get_notifications(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Query(filter): Query<NotificationFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let unread_only = filter.unread_only.unwrap_or(false);
+
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+
+    // Fetch one extra row to know whether there is a next page
+    let mut rows = sqlx::query_as::<_, NotificationFromDb>(
+        r#"
+        SELECT
+            n.id, n.notification_type, n.story_id, n.comment_id, n.event_count,
+            n.read_at, n.created_at, n.updated_at,
+            u.id as actor_id, u.username as actor_username, u.image as actor_image,
+            s.title as story_title, s.slug as story_slug
+        FROM notifications n
+        JOIN users u ON n.actor_id = u.id
+        LEFT JOIN stories s ON n.story_id = s.id
+        WHERE n.user_id = $1
+          AND ($2::TIMESTAMPTZ IS NULL OR (n.updated_at, n.id) < ($2, $3))
+          AND (NOT $4 OR n.read_at IS NULL)
+        ORDER BY n.updated_at DESC, n.id DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(cursor.map(|c| c.timestamp))
+    .bind(cursor.map(|c| c.id))
+    .bind(unread_only)
+    .bind(limit + 1)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch notifications: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let pagination = Pagination::from_rows(&mut rows, limit, |n| Cursor::new(n.updated_at, n.id));
+
+    let unread_count = count_unread(&pool, claims.sub).await?;
+
+    Ok(ApiResponse::paginated(
+        NotificationListResponse {
+            notifications: rows.into_iter().map(NotificationResponse::from).collect(),
+            unread_count,
+        },
+        pagination,
+    ))
+}
+
+/// Get the number of unread notifications
+/// GET /api/notifications/unread-count
+pub async fn get_unread_count(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let unread_count = count_unread(&pool, claims.sub).await?;
+
+    Ok(ApiResponse::success(UnreadCountResponse { unread_count }))
+}
+
+/// Mark a single notification as read
+/// POST /api/notifications/:id/read
+pub async fn mark_read(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(notification_id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    let result = sqlx::query(
+        "UPDATE notifications SET read_at = COALESCE(read_at, NOW()) WHERE id = $1 AND user_id = $2",
+    )
+    .bind(notification_id)
+    .bind(claims.sub)
+    .execute(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Notification not found".to_string()));
+    }
+
+    let unread_count = count_unread(&pool, claims.sub).await?;
+
+    Ok(ApiResponse::success(UnreadCountResponse { unread_count }))
+}
+
+/// Mark all notifications as read
+/// POST /api/notifications/read-all
+pub async fn mark_all_read(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    sqlx::query("UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL")
+        .bind(claims.sub)
+        .execute(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(UnreadCountResponse {
+        unread_count: 0,
+    }))
+}
+
+/// Get the current user's notification preferences
+/// GET /api/notifications/preferences
+pub async fn get_preferences(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+) -> Result<impl IntoResponse, AppError> {
+    let preferences = sqlx::query_as::<_, NotificationPreferences>(
+        "SELECT mention_in_app, mention_email FROM notification_preferences WHERE user_id = $1",
+    )
+    .bind(claims.sub)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .unwrap_or_default();
+
+    Ok(ApiResponse::success(preferences))
+}
+
+/// Update the current user's notification preferences
+/// PUT /api/notifications/preferences
+pub async fn update_preferences(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Json(payload): Json<UpdateNotificationPreferences>,
+) -> Result<impl IntoResponse, AppError> {
+    let preferences = sqlx::query_as::<_, NotificationPreferences>(
+        r#"
+        INSERT INTO notification_preferences (user_id, mention_in_app, mention_email)
+        VALUES ($1, COALESCE($2, TRUE), COALESCE($3, TRUE))
+        ON CONFLICT (user_id) DO UPDATE SET
+            mention_in_app = COALESCE($2, notification_preferences.mention_in_app),
+            mention_email = COALESCE($3, notification_preferences.mention_email),
+            updated_at = NOW()
+        RETURNING mention_in_app, mention_email
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(payload.mention_in_app)
+    .bind(payload.mention_email)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to update notification preferences: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(ApiResponse::success(preferences))
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::Type, PgPool};
+use uuid::Uuid;
+
+pub mod handler;
+
+/// Database model for a notification
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[allow(dead_code)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub actor_id: Uuid,
+    pub notification_type: NotificationType,
+    pub story_id: Option<Uuid>,
+    pub comment_id: Option<Uuid>,
+    pub event_count: i32,
+    pub read_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "notification_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationType {
+    Follow,
+    StoryClap,
+    CommentClap,
+    Comment,
+    Reply,
+    Mention,
+}
+
+/// Query parameters for the notifications list
+#[derive(Debug, Deserialize)]
+pub struct NotificationFilter {
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+    pub unread_only: Option<bool>,
+}
+
+/// User who triggered a notification
+#[derive(Debug, Serialize)]
+pub struct NotificationActor {
+    pub id: Uuid,
+    pub username: String,
+    pub image: Option<String>,
+}
+
+/// Story a notification is about
+#[derive(Debug, Serialize)]
+pub struct NotificationStory {
+    pub id: Uuid,
+    pub title: String,
+    pub slug: String,
+}
+
+/// Response structure for a notification
+#[derive(Debug, Serialize)]
+pub struct NotificationResponse {
+    pub id: Uuid,
+    pub notification_type: NotificationType,
+    pub actor: NotificationActor, // Most recent actor for grouped notifications
+    pub story: Option<NotificationStory>,
+    pub comment_id: Option<Uuid>,
+    pub event_count: i32, // Number of grouped events, e.g. claps
+    pub is_read: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for the cursor-paginated notifications list
+#[derive(Debug, Serialize)]
+pub struct NotificationListResponse {
+    pub notifications: Vec<NotificationResponse>,
+    pub unread_count: i64,
+}
+
+/// Response for the unread notifications badge
+#[derive(Debug, Serialize)]
+pub struct UnreadCountResponse {
+    pub unread_count: i64,
+}
+
+/// What a user wants to be notified about, and how
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct NotificationPreferences {
+    pub mention_in_app: bool,
+    pub mention_email: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            mention_in_app: true,
+            mention_email: true,
+        }
+    }
+}
+
+/// Request payload for changing notification preferences; omitted fields stay as they are
+#[derive(Debug, Deserialize)]
+pub struct UpdateNotificationPreferences {
+    pub mention_in_app: Option<bool>,
+    pub mention_email: Option<bool>,
+}
+
+/// Record a notification for `recipient_id` about something `actor_id` did.
+///
+/// Claps on the same story or comment are grouped into one unread notification.
+/// Self-notifications are skipped, and failures are only logged so that a
+/// notification problem never fails the action that triggered it.
+pub async fn notify(
+    pool: &PgPool,
+    recipient_id: Uuid,
+    actor_id: Uuid,
+    notification_type: NotificationType,
+    story_id: Option<Uuid>,
+    comment_id: Option<Uuid>,
+) {
+    if recipient_id == actor_id {
+        return;
+    }
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO notifications (user_id, actor_id, notification_type, story_id, comment_id)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (user_id, notification_type, (COALESCE(comment_id, story_id)))
+            WHERE read_at IS NULL AND notification_type IN ('story_clap', 'comment_clap')
+        DO UPDATE SET
+            event_count = notifications.event_count + 1,
+            actor_id = EXCLUDED.actor_id,
+            updated_at = NOW()
+        "#,
+    )
+    .bind(recipient_id)
+    .bind(actor_id)
+    .bind(notification_type)
+    .bind(story_id)
+    .bind(comment_id)
+    .execute(pool)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!("Failed to create notification: {:?}", e);
+    }
+}
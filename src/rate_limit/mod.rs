@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::header,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use tower::{Layer, Service};
+
+use crate::{auth::jwt::Claims, config::settings::RateLimitConfig, error::AppError};
+
+/// Most clients a limiter tracks at once; new ones are turned away while it's full
+const MAX_BUCKETS: usize = 10_000;
+
+/// How often a full bucket map may be swept of idle entries
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a rate limiter counts requests against
+#[derive(Clone)]
+enum RateLimitKey {
+    /// The client IP address
+    Ip,
+    /// The authenticated user (`Claims.sub`), or the client IP for anonymous requests
+    User { jwt_secret: String },
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Buckets {
+    map: HashMap<String, Bucket>,
+    swept_at: Instant,
+}
+
+/// Token bucket rate limiter for one route group.
+/// Cloning shares the buckets, so a single limiter can be layered onto several routes.
+#[derive(Clone)]
+pub struct RateLimiter {
+    key: RateLimitKey,
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    /// Limit requests per client IP
+    pub fn per_ip(config: RateLimitConfig) -> Self {
+        Self::new(RateLimitKey::Ip, config)
+    }
+
+    /// Limit requests per authenticated user, falling back to the client IP
+    pub fn per_user(config: RateLimitConfig, jwt_secret: &str) -> Self {
+        Self::new(
+            RateLimitKey::User {
+                jwt_secret: jwt_secret.to_string(),
+            },
+            config,
+        )
+    }
+
+    fn new(key: RateLimitKey, config: RateLimitConfig) -> Self {
+        Self {
+            key,
+            capacity: config.burst.max(1) as f64,
+            refill_per_sec: config.per_minute.max(1) as f64 / 60.0,
+            buckets: Arc::new(Mutex::new(Buckets {
+                map: HashMap::new(),
+                swept_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a token for `key`, or return the number of seconds until one is available
+    fn check(&self, key: String) -> Result<(), u64> {
+        self.check_at(key, Instant::now())
+    }
+
+    fn check_at(&self, key: String, now: Instant) -> Result<(), u64> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.map.len() >= MAX_BUCKETS && !buckets.map.contains_key(&key) {
+            if now.duration_since(buckets.swept_at) >= SWEEP_INTERVAL {
+                // Buckets that have refilled completely behave the same as missing ones
+                let (capacity, rate) = (self.capacity, self.refill_per_sec);
+                buckets.map.retain(|_, b| {
+                    b.tokens + now.duration_since(b.updated_at).as_secs_f64() * rate < capacity
+                });
+                buckets.swept_at = now;
+            }
+
+            // Still full: new clients wait for the next sweep rather than rescanning now
+            if buckets.map.len() >= MAX_BUCKETS {
+                let wait = (buckets.swept_at + SWEEP_INTERVAL).saturating_duration_since(now);
+                return Err(wait.as_secs_f64().ceil().max(1.0) as u64);
+            }
+        }
+
+        let bucket = buckets.map.entry(key).or_insert(Bucket {
+            tokens: self.capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - bucket.tokens) / self.refill_per_sec;
+            Err(wait.ceil().max(1.0) as u64)
+        }
+    }
+
+    /// Work out which bucket a request draws from
+    fn key_for(&self, req: &Request) -> String {
+        if let RateLimitKey::User { jwt_secret } = &self.key {
+            let user_id = req
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .and_then(|token| {
+                    decode::<Claims>(
+                        token,
+                        &DecodingKey::from_secret(jwt_secret.as_ref()),
+                        &Validation::default(),
+                    )
+                    .ok()
+                })
+                .map(|data| data.claims.sub);
+
+            if let Some(user_id) = user_id {
+                return format!("user:{}", user_id);
+            }
+        }
+
+        match req.extensions().get::<ConnectInfo<SocketAddr>>() {
+            Some(ConnectInfo(addr)) => format!("ip:{}", addr.ip()),
+            None => "ip:unknown".to_string(),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimiter {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.clone(),
+        }
+    }
+}
+
+/// Middleware service produced by [`RateLimiter`]
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S> Service<Request> for RateLimit<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let key = self.limiter.key_for(&req);
+
+        if let Err(retry_after) = self.limiter.check(key) {
+            return Box::pin(
+                async move { Ok(AppError::TooManyRequests(retry_after).into_response()) },
+            );
+        }
+
+        // Use the service that was polled ready and leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move { inner.call(req).await })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(burst: u32, per_minute: u32) -> RateLimiter {
+        RateLimiter::per_ip(RateLimitConfig { burst, per_minute })
+    }
+
+    #[test]
+    fn rejects_once_the_burst_is_spent() {
+        let limiter = limiter(3, 60);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at("ip:a".to_string(), now), Ok(()));
+        }
+        assert_eq!(limiter.check_at("ip:a".to_string(), now), Err(1));
+
+        // Other clients have their own bucket
+        assert_eq!(limiter.check_at("ip:b".to_string(), now), Ok(()));
+    }
+
+    #[test]
+    fn retry_after_is_the_time_until_the_next_token() {
+        let limiter = limiter(1, 6);
+        let now = Instant::now();
+
+        assert_eq!(limiter.check_at("ip:a".to_string(), now), Ok(()));
+        assert_eq!(limiter.check_at("ip:a".to_string(), now), Err(10));
+
+        let later = now + Duration::from_secs(4);
+        assert_eq!(limiter.check_at("ip:a".to_string(), later), Err(6));
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let limiter = limiter(2, 60);
+        let now = Instant::now();
+
+        assert_eq!(limiter.check_at("ip:a".to_string(), now), Ok(()));
+        assert_eq!(limiter.check_at("ip:a".to_string(), now), Ok(()));
+        assert!(limiter.check_at("ip:a".to_string(), now).is_err());
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(limiter.check_at("ip:a".to_string(), later), Ok(()));
+        assert!(limiter.check_at("ip:a".to_string(), later).is_err());
+
+        // Refilling stops at the burst size
+        let much_later = now + Duration::from_secs(600);
+        assert_eq!(limiter.check_at("ip:a".to_string(), much_later), Ok(()));
+        assert_eq!(limiter.check_at("ip:a".to_string(), much_later), Ok(()));
+        assert!(limiter.check_at("ip:a".to_string(), much_later).is_err());
+    }
+
+    #[test]
+    fn full_map_turns_new_clients_away_until_the_next_sweep() {
+        let limiter = limiter(5, 60);
+        let now = Instant::now();
+
+        for i in 0..MAX_BUCKETS {
+            assert_eq!(limiter.check_at(format!("ip:{}", i), now), Ok(()));
+        }
+
+        // Known clients keep being served, new ones wait for the sweep
+        assert_eq!(limiter.check_at("ip:0".to_string(), now), Ok(()));
+        assert_eq!(
+            limiter.check_at("ip:new".to_string(), now),
+            Err(SWEEP_INTERVAL.as_secs())
+        );
+    }
+
+    #[test]
+    fn sweep_keeps_buckets_that_are_still_refilling() {
+        let limiter = limiter(5, 60);
+        let now = Instant::now();
+
+        for i in 0..MAX_BUCKETS {
+            assert_eq!(limiter.check_at(format!("ip:{}", i), now), Ok(()));
+        }
+
+        // Shortly before the sweep, one client spends its whole burst
+        let busy_at = now + SWEEP_INTERVAL - Duration::from_secs(1);
+        for _ in 0..5 {
+            assert_eq!(limiter.check_at("ip:0".to_string(), busy_at), Ok(()));
+        }
+
+        let sweep_at = now + SWEEP_INTERVAL;
+        assert_eq!(limiter.check_at("ip:new".to_string(), sweep_at), Ok(()));
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.map.len(), 2);
+        assert!(buckets.map.contains_key("ip:0"));
+        assert!(buckets.map.contains_key("ip:new"));
+        drop(buckets);
+
+        // The busy client's bucket survived with only a second's worth of tokens
+        assert_eq!(limiter.check_at("ip:0".to_string(), sweep_at), Ok(()));
+        assert!(limiter.check_at("ip:0".to_string(), sweep_at).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::{
+        jwt,
+        roles::{Moderator, RequireRole},
+    },
+    error::AppError,
+    moderation::{self, ModerationAction},
+    reports::{
+        CreateReport, ReportCreatedResponse, ReportFilter, ReportResponse, ReportStatus,
+        ReportTarget, ResolveReport, ResolveReportResponse,
+    },
+    response::{ApiResponse, Cursor, Pagination},
+};
+
+/// Report a story, comment or user
+/// POST /api/reports
+pub async fn create_report(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Json(payload): Json<CreateReport>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    // Find who owns the target; only content readers can see is reportable
+    let owner_query = match payload.target_type {
+        ReportTarget::Story => {
+            "SELECT author_id FROM stories WHERE id = $1 AND status = 'published' AND hidden_at IS NULL"
+        }
+        ReportTarget::Comment => {
+            "SELECT author_id FROM comments WHERE id = $1 AND hidden_at IS NULL AND deleted_at IS NULL"
+        }
+        ReportTarget::User => "SELECT id FROM users WHERE id = $1",
+    };
+
+    let owner_id: Uuid = sqlx::query_scalar(owner_query)
+        .bind(payload.target_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("Report target not found".to_string()))?;
+
+    if owner_id == claims.sub {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot report yourself or your own content".to_string(),
+        ));
+    }
+
+    let details = payload
+        .details
+        .map(|d| d.trim().to_string())
+        .filter(|d| !d.is_empty());
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO reports (reporter_id, target_type, target_id, reason, details)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, status
+        "#,
+    )
+    .bind(claims.sub)
+    .bind(payload.target_type)
+    .bind(payload.target_id)
+    .bind(payload.reason)
+    .bind(details)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e: sqlx::Error| {
+        if e.to_string().contains("duplicate key value") {
+            AppError::Conflict("You have already reported this".to_string())
+        } else {
+            tracing::error!("Failed to create report: {:?}", e);
+            AppError::InternalServerError
+        }
+    })?;
+
+    Ok(ApiResponse::success(ReportCreatedResponse {
+        id: row.get("id"),
+        status: row.get("status"),
+    })
+    .created())
+}
+
+/// Moderation queue, oldest first
+/// GET /api/moderation/reports?status=open&target_type=&reason=
+pub async fn get_reports(
+    State(pool): State<PgPool>,
+    _moderator: RequireRole<Moderator>,
+    Query(filter): Query<ReportFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let status = filter.status.unwrap_or(ReportStatus::Open);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+
+    // Fetch one extra row to know whether there is a next page
+    let mut reports = sqlx::query_as::<_, ReportResponse>(
+        r#"
+        SELECT
+            r.id, r.reporter_id, u.username as reporter_username, r.target_type, r.target_id,
+            r.reason, r.details, r.status, r.resolved_by, r.resolution_note, r.resolved_at, r.created_at,
+            (SELECT COUNT(*) FROM reports o
+             WHERE o.target_type = r.target_type AND o.target_id = r.target_id AND o.status = 'open') as open_reports_for_target
+        FROM reports r
+        JOIN users u ON r.reporter_id = u.id
+        WHERE r.status = $1
+            AND ($2::report_target IS NULL OR r.target_type = $2)
+            AND ($3::report_reason IS NULL OR r.reason = $3)
+            AND ($4::TIMESTAMPTZ IS NULL OR (r.created_at, r.id) > ($4, $5))
+        ORDER BY r.created_at ASC, r.id ASC
+        LIMIT $6
+        "#,
+    )
+    .bind(status)
+    .bind(filter.target_type)
+    .bind(filter.reason)
+    .bind(cursor.as_ref().map(|c| c.timestamp))
+    .bind(cursor.as_ref().map(|c| c.id))
+    .bind(limit + 1)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch reports: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let pagination =
+        Pagination::from_rows(&mut reports, limit, |r| Cursor::new(r.created_at, r.id));
+
+    Ok(ApiResponse::paginated(reports, pagination))
+}
+
+/// Resolve a report, optionally hiding the reported content.
+/// Every open report on the same target is resolved with it.
+/// POST /api/moderation/reports/:id/resolve
+pub async fn resolve_report(
+    State(pool): State<PgPool>,
+    moderator: RequireRole<Moderator>,
+    Path(report_id): Path<Uuid>,
+    Json(payload): Json<ResolveReport>,
+) -> Result<impl IntoResponse, AppError> {
+    payload
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    let hide_content = payload.hide_content.unwrap_or(false);
+    match payload.status {
+        ReportStatus::Open => {
+            return Err(AppError::UnprocessableEntity(
+                "status must be either 'actioned' or 'dismissed'".to_string(),
+            ))
+        }
+        ReportStatus::Dismissed if hide_content => {
+            return Err(AppError::UnprocessableEntity(
+                "A dismissed report cannot hide content".to_string(),
+            ))
+        }
+        _ => {}
+    }
+
+    let note = payload
+        .note
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let report =
+        sqlx::query("SELECT target_type, target_id, status FROM reports WHERE id = $1 FOR UPDATE")
+            .bind(report_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?
+            .ok_or(AppError::NotFound("Report not found".to_string()))?;
+
+    let target_type: ReportTarget = report.get("target_type");
+    let target_id: Uuid = report.get("target_id");
+    let status: ReportStatus = report.get("status");
+
+    if status != ReportStatus::Open {
+        return Err(AppError::Conflict("Report is already resolved".to_string()));
+    }
+
+    let reason = note.clone().unwrap_or_else(|| match payload.status {
+        ReportStatus::Dismissed => "Report dismissed".to_string(),
+        _ => "Report actioned".to_string(),
+    });
+
+    if hide_content {
+        moderation::set_hidden(
+            &mut tx,
+            moderator.claims.sub,
+            target_type,
+            target_id,
+            true,
+            &reason,
+        )
+        .await?;
+    }
+
+    let resolved = sqlx::query(
+        r#"
+        UPDATE reports SET status = $1, resolved_by = $2, resolution_note = $3, resolved_at = NOW()
+        WHERE target_type = $4 AND target_id = $5 AND status = 'open'
+        "#,
+    )
+    .bind(payload.status)
+    .bind(moderator.claims.sub)
+    .bind(&note)
+    .bind(target_type)
+    .bind(target_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| AppError::InternalServerError)?;
+
+    moderation::log_action(
+        &mut *tx,
+        moderator.claims.sub,
+        ModerationAction::ResolveReport,
+        report_id,
+        None,
+        &reason,
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    Ok(ApiResponse::success(ResolveReportResponse {
+        status: payload.status,
+        content_hidden: hide_content,
+        resolved_reports: resolved.rows_affected(),
+    }))
+}
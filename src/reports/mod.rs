@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::Type;
+use uuid::Uuid;
+use validator::Validate;
+
+pub mod handler;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "report_target", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ReportTarget {
+    Story,
+    Comment,
+    User,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "report_reason", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ReportReason {
+    Spam,
+    Harassment,
+    HateSpeech,
+    Violence,
+    SexualContent,
+    Misinformation,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type, PartialEq, Eq)]
+#[sqlx(type_name = "report_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ReportStatus {
+    Open,
+    Actioned,
+    Dismissed,
+}
+
+/// Request payload for reporting a story, comment or user
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateReport {
+    pub target_type: ReportTarget,
+    pub target_id: Uuid,
+    pub reason: ReportReason,
+    #[validate(length(max = 1000, message = "Details must be at most 1000 characters"))]
+    pub details: Option<String>,
+}
+
+/// Query parameters for the moderation queue
+#[derive(Debug, Deserialize)]
+pub struct ReportFilter {
+    pub status: Option<ReportStatus>, // Defaults to open
+    pub target_type: Option<ReportTarget>,
+    pub reason: Option<ReportReason>,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Request payload for resolving a report
+#[derive(Debug, Deserialize, Validate)]
+pub struct ResolveReport {
+    pub status: ReportStatus,       // "actioned" or "dismissed"
+    pub hide_content: Option<bool>, // Hide the reported story or comment (actioned only)
+    #[validate(length(max = 1000, message = "Note must be at most 1000 characters"))]
+    pub note: Option<String>,
+}
+
+/// Response structure for a report in the moderation queue
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct ReportResponse {
+    pub id: Uuid,
+    pub reporter_id: Uuid,
+    pub reporter_username: String,
+    pub target_type: ReportTarget,
+    pub target_id: Uuid,
+    pub reason: ReportReason,
+    pub details: Option<String>,
+    pub status: ReportStatus,
+    pub resolved_by: Option<Uuid>,
+    pub resolution_note: Option<String>,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub open_reports_for_target: i64, // How many open reports the same target has
+}
+
+/// Response after filing a report
+#[derive(Debug, Serialize)]
+pub struct ReportCreatedResponse {
+    pub id: Uuid,
+    pub status: ReportStatus,
+}
+
+/// Response after resolving a report
+#[derive(Debug, Serialize)]
+pub struct ResolveReportResponse {
+    pub status: ReportStatus,
+    pub content_hidden: bool,
+    pub resolved_reports: u64, // Open reports on the same target are resolved together
+}
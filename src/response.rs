@@ -3,7 +3,12 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::AppError;
 
 /// A standardized response wrapper for the API.
 /// This ensures consistent JSON structure across all endpoints.
@@ -17,6 +22,9 @@ pub struct ApiResponse<T> {
     /// The actual data payload.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub data: Option<T>,
+    /// Pagination info for list endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
 }
 
 impl<T> ApiResponse<T>
@@ -30,6 +38,7 @@ where
             success: true,
             message: None,
             data: Some(data),
+            pagination: None,
         }
     }
 
@@ -40,6 +49,18 @@ where
             success: true,
             message: Some(message),
             data: Some(data),
+            pagination: None,
+        }
+    }
+
+    /// Creates a success response for a page of a list.
+    /// Usage: `ApiResponse::paginated(items, pagination)`
+    pub fn paginated(data: T, pagination: Pagination) -> Self {
+        Self {
+            success: true,
+            message: None,
+            data: Some(data),
+            pagination: Some(pagination),
         }
     }
 
@@ -50,6 +71,7 @@ where
             success: false,
             message: Some(message),
             data: None,
+            pagination: None,
         }
     }
 
@@ -81,6 +103,102 @@ where
     }
 }
 
+/// Pagination info shared by all list endpoints.
+/// Lists accept `limit` plus either an opaque `cursor` (preferred) or a legacy `offset`.
+#[derive(Debug, Serialize)]
+pub struct Pagination {
+    pub limit: i64,
+    pub has_more: bool,
+    /// Pass as `cursor` to fetch the next page.
+    pub next_cursor: Option<String>,
+}
+
+impl Pagination {
+    /// Builds pagination from a page fetched with `limit + 1` rows, dropping the extra row.
+    pub fn from_rows<R>(rows: &mut Vec<R>, limit: i64, cursor_of: impl Fn(&R) -> Cursor) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit.max(0) as usize);
+
+        let next_cursor = if has_more {
+            rows.last().map(|row| cursor_of(row).encode())
+        } else {
+            None
+        };
+
+        Self {
+            limit,
+            has_more,
+            next_cursor,
+        }
+    }
+}
+
+/// Opaque keyset pagination cursor: the sort key of the last item on a page.
+/// `clap_count` is only set for lists sorted by claps.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor {
+    pub clap_count: Option<i32>,
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    /// Cursor for lists sorted by a timestamp (then id)
+    pub fn new(timestamp: DateTime<Utc>, id: Uuid) -> Self {
+        Self {
+            clap_count: None,
+            timestamp,
+            id,
+        }
+    }
+
+    /// Cursor for lists sorted by clap count (then timestamp and id)
+    pub fn with_claps(clap_count: i32, timestamp: DateTime<Utc>, id: Uuid) -> Self {
+        Self {
+            clap_count: Some(clap_count),
+            timestamp,
+            id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let raw = match self.clap_count {
+            Some(claps) => format!(
+                "{}|{}|{}",
+                claps,
+                self.timestamp.timestamp_micros(),
+                self.id
+            ),
+            None => format!("{}|{}", self.timestamp.timestamp_micros(), self.id),
+        };
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    /// Decodes a cursor, checking it was issued for the same kind of sort.
+    pub fn decode(cursor: &str, by_claps: bool) -> Result<Self, AppError> {
+        let invalid = || AppError::BadRequest("Invalid cursor".to_string());
+
+        let raw = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+        let parts: Vec<&str> = raw.split('|').collect();
+
+        let (clap_count, micros, id) = match (by_claps, parts.as_slice()) {
+            (true, [claps, micros, id]) => {
+                (Some(claps.parse().map_err(|_| invalid())?), micros, id)
+            }
+            (false, [micros, id]) => (None, micros, id),
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self {
+            clap_count,
+            timestamp: DateTime::from_timestamp_micros(micros.parse().map_err(|_| invalid())?)
+                .ok_or_else(invalid)?,
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
 /// Helper struct for responses without data (e.g., just a message)
 #[derive(Serialize)]
 pub struct EmptyData;
@@ -92,6 +210,7 @@ impl ApiResponse<EmptyData> {
             success: true,
             message: Some(message),
             data: None,
+            pagination: None,
         }
     }
 
@@ -139,3 +258,75 @@ where
         (StatusCode::OK, Json(self)).into_response()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_time() -> DateTime<Utc> {
+        DateTime::from_timestamp_micros(1_767_225_600_123_456).unwrap()
+    }
+
+    #[test]
+    fn cursor_round_trips() {
+        let id = Uuid::new_v4();
+        let encoded = Cursor::new(sample_time(), id).encode();
+
+        let decoded = Cursor::decode(&encoded, false).ok().expect("valid cursor");
+        assert_eq!(decoded.clap_count, None);
+        assert_eq!(decoded.timestamp, sample_time());
+        assert_eq!(decoded.id, id);
+    }
+
+    #[test]
+    fn cursor_with_claps_round_trips() {
+        let id = Uuid::new_v4();
+        let encoded = Cursor::with_claps(42, sample_time(), id).encode();
+
+        let decoded = Cursor::decode(&encoded, true).ok().expect("valid cursor");
+        assert_eq!(decoded.clap_count, Some(42));
+        assert_eq!(decoded.timestamp, sample_time());
+        assert_eq!(decoded.id, id);
+    }
+
+    #[test]
+    fn cursor_must_match_the_sort() {
+        let id = Uuid::new_v4();
+
+        let by_time = Cursor::new(sample_time(), id).encode();
+        assert!(Cursor::decode(&by_time, true).is_err());
+
+        let by_claps = Cursor::with_claps(42, sample_time(), id).encode();
+        assert!(Cursor::decode(&by_claps, false).is_err());
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        let encode = |raw: &[u8]| URL_SAFE_NO_PAD.encode(raw);
+        let id = Uuid::new_v4();
+
+        let malformed = [
+            "".to_string(),
+            "not base64!".to_string(),
+            encode(&[0xff, 0xfe]),
+            encode(b"garbage"),
+            encode(format!("soon|{}", id).as_bytes()),
+            encode(b"1767225600123456|not-a-uuid"),
+            encode(format!("{}|{}", i64::MAX, id).as_bytes()),
+            encode(format!("1767225600123456|{}|extra", id).as_bytes()),
+        ];
+        for cursor in &malformed {
+            assert!(
+                matches!(Cursor::decode(cursor, false), Err(AppError::BadRequest(_))),
+                "accepted {:?}",
+                cursor
+            );
+        }
+
+        let bad_claps = encode(format!("many|1767225600123456|{}", id).as_bytes());
+        assert!(matches!(
+            Cursor::decode(&bad_claps, true),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::{
+    auth::jwt,
+    email::EmailService,
+    error::AppError,
+    mentions::{self, MentionTarget},
+    response::ApiResponse,
+    revisions::{
+        self, content_diff, FieldChange, RevisionDiffQuery, RevisionDiffResponse,
+        RevisionListFilter, RevisionListResponse, RevisionSummary, StoryRevision,
+    },
+    stories::handler::{get_story_response, replace_story_tags},
+};
+
+/// Revision history is only available to the story's author
+async fn ensure_author(pool: &PgPool, story_id: Uuid, user_id: Uuid) -> Result<(), AppError> {
+    let row = sqlx::query("SELECT author_id FROM stories WHERE id = $1")
+        .bind(story_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("Story not found".to_string()))?;
+
+    let author_id: Uuid = row.get("author_id");
+
+    if author_id != user_id {
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok(())
+}
+
+async fn fetch_revision(
+    pool: &PgPool,
+    story_id: Uuid,
+    revision_number: i32,
+) -> Result<StoryRevision, AppError> {
+    sqlx::query_as::<_, StoryRevision>(
+        "SELECT * FROM story_revisions WHERE story_id = $1 AND revision_number = $2",
+    )
+    .bind(story_id)
+    .bind(revision_number)
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| AppError::InternalServerError)?
+    .ok_or(AppError::NotFound("Revision not found".to_string()))
+}
+
+/// List a story's revisions, newest first
+/// GET /api/stories/:id/revisions
+pub async fn get_revisions(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(story_id): Path<Uuid>,
+    Query(filter): Query<RevisionListFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_author(&pool, story_id, claims.sub).await?;
+
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let offset = filter.offset.unwrap_or(0).max(0);
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM story_revisions WHERE story_id = $1")
+        .bind(story_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let revisions = sqlx::query_as::<_, RevisionSummary>(
+        r#"
+        SELECT revision_number, title, restored_from, created_at
+        FROM story_revisions
+        WHERE story_id = $1
+        ORDER BY revision_number DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(story_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to fetch revisions: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    let has_more = offset + (revisions.len() as i64) < total;
+
+    Ok(ApiResponse::success(RevisionListResponse {
+        revisions,
+        total,
+        has_more,
+    }))
+}
+
+/// Get a single revision with its full content
+/// GET /api/stories/:id/revisions/:revision
+pub async fn get_revision(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path((story_id, revision_number)): Path<(Uuid, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_author(&pool, story_id, claims.sub).await?;
+
+    let revision = fetch_revision(&pool, story_id, revision_number).await?;
+
+    Ok(ApiResponse::success(revision))
+}
+
+/// Diff two revisions of a story
+/// GET /api/stories/:id/revisions/diff?from=1&to=3
+pub async fn diff_revisions(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Path(story_id): Path<Uuid>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_author(&pool, story_id, claims.sub).await?;
+
+    let to = match query.to {
+        Some(to) => to,
+        None => sqlx::query_scalar::<_, Option<i32>>(
+            "SELECT MAX(revision_number) FROM story_revisions WHERE story_id = $1",
+        )
+        .bind(story_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+        .ok_or(AppError::NotFound("Revision not found".to_string()))?,
+    };
+
+    let old = fetch_revision(&pool, story_id, query.from).await?;
+    let new = fetch_revision(&pool, story_id, to).await?;
+
+    let title = (old.title != new.title).then(|| FieldChange {
+        old: Some(old.title.clone()),
+        new: Some(new.title.clone()),
+    });
+    let subtitle = (old.subtitle != new.subtitle).then(|| FieldChange {
+        old: old.subtitle.clone(),
+        new: new.subtitle.clone(),
+    });
+
+    let tags_added = new
+        .tags
+        .iter()
+        .filter(|t| !old.tags.contains(t))
+        .cloned()
+        .collect();
+    let tags_removed = old
+        .tags
+        .iter()
+        .filter(|t| !new.tags.contains(t))
+        .cloned()
+        .collect();
+
+    let content = content_diff(&old.content, &new.content);
+
+    Ok(ApiResponse::success(RevisionDiffResponse {
+        from: old.revision_number,
+        to: new.revision_number,
+        title,
+        subtitle,
+        tags_added,
+        tags_removed,
+        content,
+    }))
+}
+
+/// Restore an old revision as the current version of the story.
+/// The restore itself is recorded as a new revision, so it can be undone.
+/// POST /api/stories/:id/revisions/:revision/restore
+pub async fn restore_revision(
+    State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
+    claims: jwt::Claims,
+    Path((story_id, revision_number)): Path<(Uuid, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_author(&pool, story_id, claims.sub).await?;
+
+    let revision = fetch_revision(&pool, story_id, revision_number).await?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Lock the story so concurrent writes can't interleave revision numbers.
+    // The slug is kept so existing links keep working.
+    sqlx::query(
+        "UPDATE stories SET title = $1, subtitle = $2, content = $3, updated_at = NOW() WHERE id = $4",
+    )
+    .bind(&revision.title)
+    .bind(&revision.subtitle)
+    .bind(&revision.content)
+    .bind(story_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to restore revision: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    replace_story_tags(&mut tx, story_id, &revision.tags).await?;
+    mentions::sync_story(&mut tx, story_id, &revision.content).await?;
+
+    revisions::record(
+        &mut tx,
+        story_id,
+        claims.sub,
+        Some(revision.revision_number),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    mentions::notify(&pool, &email_service, MentionTarget::Story(story_id)).await;
+
+    get_story_response(&pool, story_id, Some(claims.sub)).await
+}
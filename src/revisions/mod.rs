@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub mod handler;
+
+/// Database model for a story revision
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoryRevision {
+    pub id: Uuid,
+    pub story_id: Uuid,
+    pub revision_number: i32,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub content: serde_json::Value,
+    pub tags: Vec<String>,
+    pub created_by: Uuid,
+    pub restored_from: Option<i32>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Query parameters for the paginated revision list
+#[derive(Debug, Deserialize)]
+pub struct RevisionListFilter {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Query parameters for diffing two revisions
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    pub to: Option<i32>, // Defaults to the latest revision
+}
+
+/// Revision metadata without the content, for listing
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct RevisionSummary {
+    pub revision_number: i32,
+    pub title: String,
+    pub restored_from: Option<i32>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Response for the paginated revision list
+#[derive(Debug, Serialize)]
+pub struct RevisionListResponse {
+    pub revisions: Vec<RevisionSummary>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+/// Old and new value of a changed field
+#[derive(Debug, Serialize)]
+pub struct FieldChange {
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// One line of the content diff
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// Response for a diff between two revisions; unchanged fields are null
+#[derive(Debug, Serialize)]
+pub struct RevisionDiffResponse {
+    pub from: i32,
+    pub to: i32,
+    pub title: Option<FieldChange>,
+    pub subtitle: Option<FieldChange>,
+    pub tags_added: Vec<String>,
+    pub tags_removed: Vec<String>,
+    pub content: Vec<DiffLine>, // Line diff of the story text, one line per block
+}
+
+/// Snapshot the story's current title, subtitle, content and tags as its next revision.
+///
+/// Must run in the same transaction as the write it records, after the story row
+/// has been locked, so revision numbers stay sequential.
+pub async fn record(
+    conn: &mut PgConnection,
+    story_id: Uuid,
+    created_by: Uuid,
+    restored_from: Option<i32>,
+) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        INSERT INTO story_revisions (story_id, revision_number, title, subtitle, content, tags, created_by, restored_from)
+        SELECT
+            s.id,
+            COALESCE((SELECT MAX(revision_number) FROM story_revisions WHERE story_id = s.id), 0) + 1,
+            s.title, s.subtitle, s.content,
+            COALESCE(
+                (SELECT ARRAY_AGG(t.name ORDER BY t.name) FROM story_tags st JOIN tags t ON st.tag_id = t.id WHERE st.story_id = s.id),
+                '{}'
+            ),
+            $2, $3
+        FROM stories s
+        WHERE s.id = $1
+        "#,
+    )
+    .bind(story_id)
+    .bind(created_by)
+    .bind(restored_from)
+    .execute(conn)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to record story revision: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(())
+}
+
+/// Flatten story content into text lines, one per text block (paragraph, heading, ...)
+pub fn content_lines(content: &serde_json::Value) -> Vec<String> {
+    fn walk(node: &serde_json::Value, lines: &mut Vec<String>) {
+        if let Some(text) = node.as_str() {
+            lines.extend(text.lines().map(str::to_string));
+            return;
+        }
+
+        let Some(children) = node.get("content").and_then(|c| c.as_array()) else {
+            return;
+        };
+
+        // A block whose children carry text is one line; otherwise descend into it
+        if children.iter().any(|c| c.get("text").is_some()) {
+            let line: String = children
+                .iter()
+                .filter_map(|c| c.get("text").and_then(|t| t.as_str()))
+                .collect();
+            lines.push(line);
+        } else {
+            for child in children {
+                walk(child, lines);
+            }
+        }
+    }
+
+    let mut lines = Vec::new();
+    walk(content, &mut lines);
+    lines
+}
+
+/// Line diff between two versions of story content
+pub fn content_diff(old: &serde_json::Value, new: &serde_json::Value) -> Vec<DiffLine> {
+    // Every line ends in a newline, or appending after the last one would show it as changed
+    let text = |content| -> String {
+        content_lines(content)
+            .into_iter()
+            .map(|line| line + "\n")
+            .collect()
+    };
+    let (old_text, new_text) = (text(old), text(new));
+
+    TextDiff::from_lines(&old_text, &new_text)
+        .iter_all_changes()
+        .map(|change| DiffLine {
+            op: match change.tag() {
+                ChangeTag::Equal => DiffOp::Equal,
+                ChangeTag::Insert => DiffOp::Insert,
+                ChangeTag::Delete => DiffOp::Delete,
+            },
+            text: change.value().trim_end_matches('\n').to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn doc(paragraphs: &[&str]) -> serde_json::Value {
+        json!({
+            "type": "doc",
+            "content": paragraphs
+                .iter()
+                .map(|p| json!({ "type": "paragraph", "content": [{ "type": "text", "text": p }] }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    #[test]
+    fn rich_text_has_one_line_per_block() {
+        let content = json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "content": [{ "type": "text", "text": "Title" }] },
+                {
+                    "type": "paragraph",
+                    "content": [
+                        { "type": "text", "text": "Hello " },
+                        { "type": "text", "text": "world", "marks": [{ "type": "bold" }] },
+                    ],
+                },
+                {
+                    "type": "bulletList",
+                    "content": [
+                        { "type": "listItem", "content": [
+                            { "type": "paragraph", "content": [{ "type": "text", "text": "one" }] },
+                        ] },
+                        { "type": "listItem", "content": [
+                            { "type": "paragraph", "content": [{ "type": "text", "text": "two" }] },
+                        ] },
+                    ],
+                },
+            ],
+        });
+
+        assert_eq!(
+            content_lines(&content),
+            ["Title", "Hello world", "one", "two"]
+        );
+    }
+
+    #[test]
+    fn plain_string_content_splits_on_newlines() {
+        assert_eq!(
+            content_lines(&json!("first line\nsecond line")),
+            ["first line", "second line"]
+        );
+        assert!(content_lines(&json!(null)).is_empty());
+    }
+
+    #[test]
+    fn diff_marks_added_and_removed_lines() {
+        let old = doc(&["intro", "old middle", "outro"]);
+        let new = doc(&["intro", "new middle", "outro", "postscript"]);
+
+        let diff: Vec<(DiffOp, String)> = content_diff(&old, &new)
+            .into_iter()
+            .map(|line| (line.op, line.text))
+            .collect();
+
+        assert_eq!(
+            diff,
+            [
+                (DiffOp::Equal, "intro".to_string()),
+                (DiffOp::Delete, "old middle".to_string()),
+                (DiffOp::Insert, "new middle".to_string()),
+                (DiffOp::Equal, "outro".to_string()),
+                (DiffOp::Insert, "postscript".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_content_has_no_changes() {
+        let content = doc(&["same", "text"]);
+
+        assert!(content_diff(&content, &content)
+            .iter()
+            .all(|line| line.op == DiffOp::Equal));
+    }
+}
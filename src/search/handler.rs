@@ -0,0 +1,364 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::jwt,
+    error::AppError,
+    mentions::MentionSpan,
+    response::ApiResponse,
+    search::{
+        SearchQuery, SearchResponse, SearchSection, StoryHighlight, StorySearchResult,
+        TagSearchResult, UserSearchResult,
+    },
+    stories::{AuthorResponse, StoryResponse, StoryStatus},
+};
+
+/// Helper struct for fetching matching stories with rank and highlights
+#[derive(FromRow)]
+struct StorySearchRow {
+    id: Uuid,
+    title: String,
+    subtitle: Option<String>,
+    content: serde_json::Value,
+    slug: String,
+    status: StoryStatus,
+    clap_count: i32,
+    created_at: chrono::DateTime<chrono::Utc>,
+    published_at: Option<chrono::DateTime<chrono::Utc>>,
+    scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
+    author_id: Uuid,
+    username: String,
+    bio: Option<String>,
+    image: Option<String>,
+    tags: Vec<String>,
+    mentions: sqlx::types::Json<Vec<MentionSpan>>,
+    is_bookmarked: bool,
+    rank: f32,
+    title_highlight: String,
+    snippet: String,
+}
+
+impl From<StorySearchRow> for StorySearchResult {
+    fn from(s: StorySearchRow) -> Self {
+        StorySearchResult {
+            story: StoryResponse {
+                id: s.id,
+                author: AuthorResponse {
+                    id: s.author_id,
+                    username: s.username,
+                    bio: s.bio,
+                    image: s.image,
+                },
+                title: s.title,
+                subtitle: s.subtitle,
+                content: s.content,
+                slug: s.slug,
+                status: s.status,
+                clap_count: s.clap_count,
+                tags: s.tags,
+                mentions: s.mentions.0,
+                is_bookmarked: s.is_bookmarked,
+                created_at: s.created_at,
+                published_at: s.published_at,
+                scheduled_for: s.scheduled_for,
+            },
+            rank: s.rank,
+            highlight: StoryHighlight {
+                title: s.title_highlight,
+                snippet: s.snippet,
+            },
+        }
+    }
+}
+
+/// Helper struct for fetching matching users
+#[derive(FromRow)]
+struct UserSearchRow {
+    id: Uuid,
+    username: String,
+    bio: Option<String>,
+    image: Option<String>,
+    followers_count: i32,
+}
+
+impl From<UserSearchRow> for UserSearchResult {
+    fn from(u: UserSearchRow) -> Self {
+        UserSearchResult {
+            id: u.id,
+            username: u.username,
+            bio: u.bio,
+            image: u.image,
+            followers_count: u.followers_count as i64,
+        }
+    }
+}
+
+/// Helper struct for fetching matching tags
+#[derive(FromRow)]
+struct TagSearchRow {
+    id: Uuid,
+    name: String,
+    stories_count: i64,
+}
+
+impl From<TagSearchRow> for TagSearchResult {
+    fn from(t: TagSearchRow) -> Self {
+        TagSearchResult {
+            id: t.id,
+            name: t.name,
+            stories_count: t.stories_count,
+        }
+    }
+}
+
+/// Search stories, users and tags
+/// GET /api/search?q=
+///
+/// Stories are ranked by full-text relevance over title, subtitle and content.
+/// Users match on username or bio, tags on name. `type` restricts the search
+/// to a single section, which is how clients page through one kind of result.
+pub async fn search(
+    State(pool): State<PgPool>,
+    claims: Option<jwt::Claims>,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    query
+        .validate()
+        .map_err(|e| AppError::UnprocessableEntity(e.to_string()))?;
+
+    let q = query.q.trim().to_string();
+    if q.is_empty() {
+        return Err(AppError::UnprocessableEntity(
+            "Search query cannot be empty".to_string(),
+        ));
+    }
+
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let (search_stories, search_users, search_tags) = match query.r#type.as_deref() {
+        None | Some("all") => (true, true, true),
+        Some("stories") => (true, false, false),
+        Some("users") => (false, true, false),
+        Some("tags") => (false, false, true),
+        Some(_) => {
+            return Err(AppError::UnprocessableEntity(
+                "type must be one of: all, stories, users, tags".to_string(),
+            ))
+        }
+    };
+
+    let stories = if search_stories {
+        Some(search_stories_section(&pool, &q, claims.map(|c| c.sub), limit, offset).await?)
+    } else {
+        None
+    };
+
+    let users = if search_users {
+        Some(search_users_section(&pool, &q, limit, offset).await?)
+    } else {
+        None
+    };
+
+    let tags = if search_tags {
+        Some(search_tags_section(&pool, &q, limit, offset).await?)
+    } else {
+        None
+    };
+
+    Ok(ApiResponse::success(SearchResponse {
+        query: q,
+        stories,
+        users,
+        tags,
+    }))
+}
+
+async fn search_stories_section(
+    pool: &PgPool,
+    q: &str,
+    viewer_id: Option<Uuid>,
+    limit: i64,
+    offset: i64,
+) -> Result<SearchSection<StorySearchResult>, AppError> {
+    let total: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM stories
+        WHERE status = 'published' AND hidden_at IS NULL AND search_vector @@ websearch_to_tsquery('english', $1)
+        "#,
+    )
+    .bind(q)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Story search count error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    // Rank and paginate first so highlights are only built for the returned page.
+    // Highlights are HTML: the text is escaped so the only markup is <mark>.
+    let rows = sqlx::query_as::<_, StorySearchRow>(
+        r#"
+        WITH q AS (
+            SELECT websearch_to_tsquery('english', $1) AS query
+        ),
+        matches AS (
+            SELECT s.id, ts_rank_cd(s.search_vector, q.query) AS rank
+            FROM stories s, q
+            WHERE s.status = 'published' AND s.hidden_at IS NULL AND s.search_vector @@ q.query
+            ORDER BY rank DESC, s.created_at DESC
+            LIMIT $2 OFFSET $3
+        )
+        SELECT
+            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count, s.created_at, s.published_at, s.scheduled_for,
+            u.id as author_id, u.username, u.bio, u.image,
+            story_mentions(s.id) as mentions,
+            COALESCE(
+                (SELECT ARRAY_AGG(t.name) FROM story_tags st JOIN tags t ON st.tag_id = t.id WHERE st.story_id = s.id),
+                '{}'
+            ) as tags,
+            EXISTS(SELECT 1 FROM bookmarks b WHERE b.story_id = s.id AND b.user_id = $4) as is_bookmarked,
+            m.rank,
+            ts_headline('english', escape_html(s.title), q.query,
+                'HighlightAll=true, StartSel=<mark>, StopSel=</mark>') as title_highlight,
+            ts_headline('english',
+                escape_html(COALESCE(s.subtitle, '') || ' ' || COALESCE(story_content_text(s.content), '')),
+                q.query,
+                'StartSel=<mark>, StopSel=</mark>, MinWords=15, MaxWords=35, MaxFragments=2, FragmentDelimiter=" ... "') as snippet
+        FROM matches m
+        JOIN stories s ON s.id = m.id
+        JOIN users u ON s.author_id = u.id
+        CROSS JOIN q
+        ORDER BY m.rank DESC, s.created_at DESC
+        "#,
+    )
+    .bind(q)
+    .bind(limit)
+    .bind(offset)
+    .bind(viewer_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Story search error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(SearchSection {
+        results: rows.into_iter().map(StorySearchResult::from).collect(),
+        total,
+        has_more: (offset + limit) < total,
+    })
+}
+
+async fn search_users_section(
+    pool: &PgPool,
+    q: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<SearchSection<UserSearchResult>, AppError> {
+    let pattern = format!("%{}%", escape_like(q));
+
+    let total: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM users
+        WHERE username ILIKE $1 OR search_vector @@ websearch_to_tsquery('english', $2)
+        "#,
+    )
+    .bind(&pattern)
+    .bind(q)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("User search count error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    // Exact username matches first, then prefix matches, then bio relevance
+    let rows = sqlx::query_as::<_, UserSearchRow>(
+        r#"
+        SELECT id, username, bio, image, followers_count
+        FROM users
+        WHERE username ILIKE $1 OR search_vector @@ websearch_to_tsquery('english', $2)
+        ORDER BY
+            LOWER(username) = LOWER($2) DESC,
+            username ILIKE $3 DESC,
+            ts_rank(search_vector, websearch_to_tsquery('english', $2)) DESC,
+            followers_count DESC
+        LIMIT $4 OFFSET $5
+        "#,
+    )
+    .bind(&pattern)
+    .bind(q)
+    .bind(format!("{}%", escape_like(q)))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("User search error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(SearchSection {
+        results: rows.into_iter().map(UserSearchResult::from).collect(),
+        total,
+        has_more: (offset + limit) < total,
+    })
+}
+
+async fn search_tags_section(
+    pool: &PgPool,
+    q: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<SearchSection<TagSearchResult>, AppError> {
+    let pattern = format!("%{}%", escape_like(&q.to_lowercase()));
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tags WHERE name ILIKE $1")
+        .bind(&pattern)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    let rows = sqlx::query_as::<_, TagSearchRow>(
+        r#"
+        SELECT t.id, t.name, COUNT(s.id) as stories_count
+        FROM tags t
+        LEFT JOIN story_tags st ON t.id = st.tag_id
+        LEFT JOIN stories s ON st.story_id = s.id AND s.status = 'published' AND s.hidden_at IS NULL
+        WHERE t.name ILIKE $1
+        GROUP BY t.id
+        ORDER BY t.name = LOWER($2) DESC, stories_count DESC, t.name ASC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(&pattern)
+    .bind(q)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Tag search error: {:?}", e);
+        AppError::InternalServerError
+    })?;
+
+    Ok(SearchSection {
+        results: rows.into_iter().map(TagSearchResult::from).collect(),
+        total,
+        has_more: (offset + limit) < total,
+    })
+}
+
+/// Escape LIKE wildcards so user input only matches literally
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::stories::StoryResponse;
+
+pub mod handler;
+
+/// Query parameters for search
+#[derive(Debug, Deserialize, Validate)]
+pub struct SearchQuery {
+    #[validate(length(
+        min = 1,
+        max = 200,
+        message = "Search query must be between 1 and 200 characters"
+    ))]
+    pub q: String,
+    pub r#type: Option<String>, // "all" (default), "stories", "users" or "tags"
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// Highlighted fragments for a matching story
+#[derive(Debug, Serialize)]
+pub struct StoryHighlight {
+    pub title: String,
+    pub snippet: String,
+}
+
+/// A story matching a search, with its rank and highlighted fragments
+#[derive(Debug, Serialize)]
+pub struct StorySearchResult {
+    #[serde(flatten)]
+    pub story: StoryResponse,
+    pub rank: f32,
+    pub highlight: StoryHighlight,
+}
+
+/// A user matching a search
+#[derive(Debug, Serialize)]
+pub struct UserSearchResult {
+    pub id: Uuid,
+    pub username: String,
+    pub bio: Option<String>,
+    pub image: Option<String>,
+    pub followers_count: i64,
+}
+
+/// A tag matching a search
+#[derive(Debug, Serialize)]
+pub struct TagSearchResult {
+    pub id: Uuid,
+    pub name: String,
+    pub stories_count: i64,
+}
+
+/// Paginated results for one kind of search result
+#[derive(Debug, Serialize)]
+pub struct SearchSection<T> {
+    pub results: Vec<T>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+/// Response for a search across stories, users and tags
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stories: Option<SearchSection<StorySearchResult>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub users: Option<SearchSection<UserSearchResult>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<SearchSection<TagSearchResult>>,
+}
@@ -4,21 +4,29 @@ use axum::{
     Json,
 };
 use slug::slugify;
-use sqlx::{FromRow, PgPool, Row};
+use sqlx::{FromRow, PgConnection, PgPool, Postgres, QueryBuilder, Row};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    auth::jwt,
+    auth::{jwt, Role},
+    blocks,
+    email::EmailService,
     error::AppError,
-    response::ApiResponse,
+    mentions::{self, MentionSpan, MentionTarget},
+    moderation::{self, ModerationAction, ModerationReason},
+    notifications::{self, NotificationType},
+    response::{ApiResponse, Cursor, Pagination},
+    revisions,
     stories::{
-        AuthorResponse, CreateStory, Story, StoryFilter, StoryResponse, StoryStatus, UpdateStory,
+        AuthorResponse, CreateStory, MyStoriesFilter, Story, StoryFilter, StoryResponse,
+        StoryStatus, UpdateStory,
     },
 };
 
 pub async fn create_story(
     State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
     claims: jwt::Claims,
     Json(payload): Json<CreateStory>,
 ) -> Result<impl IntoResponse, AppError> {
@@ -42,13 +50,17 @@ pub async fn create_story(
         suffix += 1;
     }
 
+    let now = chrono::Utc::now();
+    validate_schedule(payload.publish, payload.scheduled_for, now)?;
+
     let status = if payload.publish {
         StoryStatus::Published
+    } else if payload.scheduled_for.is_some() {
+        StoryStatus::Scheduled
     } else {
         StoryStatus::Draft
     };
 
-    let now = chrono::Utc::now();
     let published_at = if payload.publish { Some(now) } else { None };
 
     let mut tx = pool
@@ -58,8 +70,8 @@ pub async fn create_story(
 
     let story = sqlx::query_as::<_, Story>(
         r#"
-        INSERT INTO stories (author_id, title, subtitle, content, slug, status, published_at, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        INSERT INTO stories (author_id, title, subtitle, content, slug, status, published_at, scheduled_for, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         RETURNING *
         "#,
     )
@@ -70,6 +82,7 @@ pub async fn create_story(
     .bind(&slug)
     .bind(&status)
     .bind(published_at)
+    .bind(payload.scheduled_for)
     .bind(now)
     .bind(now)
     .fetch_one(&mut *tx)
@@ -105,19 +118,28 @@ pub async fn create_story(
         .map_err(|_| AppError::InternalServerError)?;
     }
 
+    mentions::sync_story(&mut tx, story.id, &payload.content).await?;
+    revisions::record(&mut tx, story.id, claims.sub, None).await?;
+
     tx.commit()
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
+    // Drafts and scheduled stories notify the people they mention once published
+    mentions::notify(&pool, &email_service, MentionTarget::Story(story.id)).await;
+
     // Fetch complete story with tags and author
-    get_story_response(&pool, story.id).await
+    get_story_response(&pool, story.id, Some(claims.sub)).await
 }
 
 pub async fn get_story(
     State(pool): State<PgPool>,
+    claims: Option<jwt::Claims>,
     Path(slug): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
-    let row = sqlx::query("SELECT id FROM stories WHERE slug = $1")
+    let row = sqlx::query(
+        "SELECT id, author_id, status, hidden_at IS NOT NULL as hidden FROM stories WHERE slug = $1",
+    )
         .bind(&slug)
         .fetch_optional(&pool)
         .await
@@ -125,23 +147,46 @@ pub async fn get_story(
         .ok_or(AppError::NotFound("Story not found".to_string()))?;
 
     let story_id: Uuid = row.get("id");
+    let author_id: Uuid = row.get("author_id");
+    let status: StoryStatus = row.get("status");
+    let hidden: bool = row.get("hidden");
+    let viewer_id = claims.as_ref().map(|c| c.sub);
+    let is_author = viewer_id == Some(author_id);
+
+    // Unpublished stories are only visible to their author; respond as if they don't exist
+    if status != StoryStatus::Published && !is_author {
+        return Err(AppError::NotFound("Story not found".to_string()));
+    }
 
-    get_story_response(&pool, story_id).await
+    // Stories hidden by moderation stay visible to their author and to moderators
+    let is_moderator = claims.is_some_and(|c| c.role >= Role::Moderator);
+    if hidden && !is_author && !is_moderator {
+        return Err(AppError::NotFound("Story not found".to_string()));
+    }
+
+    get_story_response(&pool, story_id, viewer_id).await
 }
 
 pub async fn update_story(
     State(pool): State<PgPool>,
+    State(email_service): State<EmailService>,
     claims: jwt::Claims,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateStory>,
 ) -> Result<impl IntoResponse, AppError> {
+    validate_schedule(
+        payload.publish.is_some(),
+        payload.scheduled_for,
+        chrono::Utc::now(),
+    )?;
+
     let mut tx = pool
         .begin()
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    // Verify ownership
-    let row = sqlx::query("SELECT author_id FROM stories WHERE id = $1")
+    // Verify ownership, locking the story until the revision is recorded
+    let row = sqlx::query("SELECT author_id, status FROM stories WHERE id = $1 FOR UPDATE")
         .bind(id)
         .fetch_optional(&mut *tx)
         .await
@@ -154,6 +199,13 @@ pub async fn update_story(
         return Err(AppError::Unauthorized);
     }
 
+    let current_status: StoryStatus = row.get("status");
+    if payload.scheduled_for.is_some() && current_status == StoryStatus::Published {
+        return Err(AppError::UnprocessableEntity(
+            "Story is already published".to_string(),
+        ));
+    }
+
     if let Some(title) = &payload.title {
         let _ = sqlx::query(
             "UPDATE stories SET title = $1, slug = $2, updated_at = NOW() WHERE id = $3",
@@ -179,6 +231,8 @@ pub async fn update_story(
             .bind(id)
             .execute(&mut *tx)
             .await;
+
+        mentions::sync_story(&mut tx, id, content).await?;
     }
 
     if let Some(publish) = payload.publish {
@@ -192,8 +246,9 @@ pub async fn update_story(
         } else {
             None
         };
+        // Publishing or unpublishing cancels any pending schedule
         let _ = sqlx::query(
-            "UPDATE stories SET status = $1, published_at = COALESCE(published_at, $2), updated_at = NOW() WHERE id = $3",
+            "UPDATE stories SET status = $1, published_at = COALESCE(published_at, $2), scheduled_for = NULL, updated_at = NOW() WHERE id = $3",
         )
         .bind(&status)
         .bind(published_at)
@@ -202,49 +257,105 @@ pub async fn update_story(
         .await;
     }
 
+    if let Some(scheduled_for) = payload.scheduled_for {
+        sqlx::query(
+            "UPDATE stories SET status = $1, scheduled_for = $2, updated_at = NOW() WHERE id = $3",
+        )
+        .bind(StoryStatus::Scheduled)
+        .bind(scheduled_for)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+    }
+
     if let Some(tags) = payload.tags {
-        // Clear existing tags
-        sqlx::query("DELETE FROM story_tags WHERE story_id = $1")
-            .bind(id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| AppError::InternalServerError)?;
+        replace_story_tags(&mut tx, id, &tags).await?;
+    }
 
-        // Re-add tags
-        for tag_name in tags {
-            let tag_clean = tag_name.trim().to_lowercase();
-            let tag_row = sqlx::query(
-                "INSERT INTO tags (name) VALUES ($1) ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name RETURNING id",
-            )
-            .bind(&tag_clean)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(|_| AppError::InternalServerError)?;
+    revisions::record(&mut tx, id, claims.sub, None).await?;
 
-            let tag_id: Uuid = tag_row.get("id");
+    tx.commit()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
 
-            sqlx::query(
-                "INSERT INTO story_tags (story_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
-            )
-            .bind(id)
-            .bind(tag_id)
-            .execute(&mut *tx)
-            .await
-            .map_err(|_| AppError::InternalServerError)?;
-        }
+    // Covers both new mentions and a draft being published
+    mentions::notify(&pool, &email_service, MentionTarget::Story(id)).await;
+
+    get_story_response(&pool, id, Some(claims.sub)).await
+}
+
+/// A schedule must be in the future and can't be combined with an explicit publish flag
+fn validate_schedule(
+    publish: bool,
+    scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<(), AppError> {
+    let Some(scheduled_for) = scheduled_for else {
+        return Ok(());
+    };
+
+    if publish {
+        return Err(AppError::UnprocessableEntity(
+            "Use either publish or scheduled_for, not both".to_string(),
+        ));
     }
 
-    tx.commit()
+    if scheduled_for <= now {
+        return Err(AppError::UnprocessableEntity(
+            "scheduled_for must be in the future".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Replace a story's tags, creating any tags that don't exist yet
+pub(crate) async fn replace_story_tags(
+    conn: &mut PgConnection,
+    story_id: Uuid,
+    tags: &[String],
+) -> Result<(), AppError> {
+    // Clear existing tags
+    sqlx::query("DELETE FROM story_tags WHERE story_id = $1")
+        .bind(story_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    // Re-add tags
+    for tag_name in tags {
+        let tag_clean = tag_name.trim().to_lowercase();
+        let tag_row = sqlx::query(
+            "INSERT INTO tags (name) VALUES ($1) ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name RETURNING id",
+        )
+        .bind(&tag_clean)
+        .fetch_one(&mut *conn)
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
-    get_story_response(&pool, id).await
+        let tag_id: Uuid = tag_row.get("id");
+
+        sqlx::query(
+            "INSERT INTO story_tags (story_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(story_id)
+        .bind(tag_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+    }
+
+    Ok(())
 }
 
+/// Delete a story. Moderators may delete other users' stories with an audit reason.
+/// DELETE /api/stories/:id?reason=...
 pub async fn delete_story(
     State(pool): State<PgPool>,
     claims: jwt::Claims,
     Path(id): Path<Uuid>,
+    Query(moderation): Query<ModerationReason>,
 ) -> Result<impl IntoResponse, AppError> {
     let row = sqlx::query("SELECT author_id FROM stories WHERE id = $1")
         .bind(id)
@@ -255,60 +366,213 @@ pub async fn delete_story(
 
     let story_author_id: Uuid = row.get("author_id");
 
-    if story_author_id != claims.sub {
+    let is_author = story_author_id == claims.sub;
+    if !is_author && claims.role < Role::Moderator {
         return Err(AppError::Unauthorized);
     }
 
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    if !is_author {
+        let reason = moderation::require_reason(moderation.reason)?;
+        moderation::log_action(
+            &mut *tx,
+            claims.sub,
+            ModerationAction::DeleteStory,
+            id,
+            Some(story_author_id),
+            &reason,
+        )
+        .await?;
+    }
+
     sqlx::query("DELETE FROM stories WHERE id = $1")
         .bind(id)
-        .execute(&pool)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| AppError::InternalServerError)?;
+
+    tx.commit()
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
     Ok(ApiResponse::ok("Story deleted".to_string()))
 }
 
+/// Append the optional feed filters from `StoryFilter` as `AND` conditions.
+/// Every user-supplied value is sent as a bound parameter, never spliced into the SQL.
+pub(crate) fn push_story_filters(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    filter: &StoryFilter,
+) -> Result<(), AppError> {
+    let tag_names = filter.tag_names();
+    if tag_names.len() > 10 {
+        return Err(AppError::UnprocessableEntity(
+            "Maximum 10 tags allowed per filter".to_string(),
+        ));
+    }
+
+    let match_all = match filter.tag_mode.as_deref() {
+        None | Some("any") => false,
+        Some("all") => true,
+        Some(_) => {
+            return Err(AppError::UnprocessableEntity(
+                "tag_mode must be either 'any' or 'all'".to_string(),
+            ))
+        }
+    };
+
+    // Filter with EXISTS rather than on the joined tags so the story's tag list stays complete
+    if !tag_names.is_empty() {
+        if match_all {
+            let count = tag_names.len() as i64;
+            qb.push(
+                " AND (SELECT COUNT(*) FROM story_tags fst JOIN tags ft ON fst.tag_id = ft.id WHERE fst.story_id = s.id AND ft.name = ANY(",
+            );
+            qb.push_bind(tag_names);
+            qb.push(")) = ");
+            qb.push_bind(count);
+        } else {
+            qb.push(
+                " AND EXISTS (SELECT 1 FROM story_tags fst JOIN tags ft ON fst.tag_id = ft.id WHERE fst.story_id = s.id AND ft.name = ANY(",
+            );
+            qb.push_bind(tag_names);
+            qb.push("))");
+        }
+    }
+
+    if let Some(author_id) = filter.author_id {
+        qb.push(" AND s.author_id = ");
+        qb.push_bind(author_id);
+    }
+
+    if let Some(author) = &filter.author {
+        qb.push(" AND u.username = ");
+        qb.push_bind(author.clone());
+    }
+
+    if let Some(published_after) = filter.published_after {
+        qb.push(" AND s.published_at >= ");
+        qb.push_bind(published_after);
+    }
+
+    if let Some(published_before) = filter.published_before {
+        qb.push(" AND s.published_at < ");
+        qb.push_bind(published_before);
+    }
+
+    Ok(())
+}
+
+/// Leave out stories by authors the viewer muted or blocked
+pub(crate) fn push_viewer_exclusions(qb: &mut QueryBuilder<'_, Postgres>, viewer_id: Uuid) {
+    qb.push(" AND NOT EXISTS (SELECT 1 FROM user_mutes m WHERE m.muter_id = ");
+    qb.push_bind(viewer_id);
+    qb.push(" AND m.muted_id = s.author_id)");
+    qb.push(" AND NOT EXISTS (SELECT 1 FROM user_blocks b WHERE b.blocker_id = ");
+    qb.push_bind(viewer_id);
+    qb.push(" AND b.blocked_id = s.author_id)");
+}
+
+/// Append the keyset condition for a story list cursor
+pub(crate) fn push_story_keyset(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    cursor: Option<Cursor>,
+    by_claps: bool,
+) {
+    let Some(cursor) = cursor else {
+        return;
+    };
+
+    if by_claps {
+        qb.push(" AND (s.clap_count, COALESCE(s.published_at, s.created_at), s.id) < (");
+        qb.push_bind(cursor.clap_count);
+        qb.push(", ");
+    } else {
+        qb.push(" AND (COALESCE(s.published_at, s.created_at), s.id) < (");
+    }
+    qb.push_bind(cursor.timestamp);
+    qb.push(", ");
+    qb.push_bind(cursor.id);
+    qb.push(")");
+}
+
+/// ORDER BY for a story list (id breaks ties so keyset pagination is stable).
+/// Stories go by when they were published, so a draft published today comes before
+/// one written later but published earlier; unpublished ones by when they were created.
+/// Cursors carry the same time, see `story_sort_time`.
+pub(crate) fn story_order_clause(by_claps: bool) -> &'static str {
+    if by_claps {
+        "s.clap_count DESC, COALESCE(s.published_at, s.created_at) DESC, s.id DESC"
+    } else {
+        "COALESCE(s.published_at, s.created_at) DESC, s.id DESC" // Default latest
+    }
+}
+
+/// The time a story is ordered by in lists, for its cursor
+pub(crate) fn story_sort_time(
+    published_at: Option<chrono::DateTime<chrono::Utc>>,
+    created_at: chrono::DateTime<chrono::Utc>,
+) -> chrono::DateTime<chrono::Utc> {
+    published_at.unwrap_or(created_at)
+}
+
 pub async fn get_feed(
     State(pool): State<PgPool>,
+    claims: Option<jwt::Claims>,
     Query(filter): Query<StoryFilter>,
 ) -> Result<impl IntoResponse, AppError> {
-    let limit = filter.limit.unwrap_or(20).min(100);
-    let offset = filter.offset.unwrap_or(0);
-
-    // Sort
-    let order_clause = match filter.sort.as_deref() {
-        Some("claps") => "s.clap_count DESC, s.created_at DESC",
-        _ => "s.created_at DESC", // Default latest
-    };
-
-    // Filter by tag
-    let where_clause = if let Some(tag) = &filter.tag {
-        format!("WHERE t.name = '{}' AND s.status = 'published'", tag)
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let by_claps = filter.sort.as_deref() == Some("claps");
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, by_claps))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
     } else {
-        "WHERE s.status = 'published'".to_string()
+        filter.offset.unwrap_or(0)
     };
 
-    let query_str = format!(
+    let mut qb = QueryBuilder::<Postgres>::new(
         r#"
         SELECT 
-            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count, s.created_at as created_at, s.updated_at, s.published_at, s.author_id,
+            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count, s.created_at as created_at, s.updated_at, s.published_at, s.scheduled_for, s.author_id,
             u.username, u.bio, u.image,
-            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{{}}') as tags
+            story_mentions(s.id) as mentions,
+            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{}') as tags,
+            EXISTS(SELECT 1 FROM bookmarks b WHERE b.story_id = s.id AND b.user_id = "#,
+    );
+    let viewer_id = claims.map(|c| c.sub);
+    qb.push_bind(viewer_id);
+    qb.push(
+        r#") as is_bookmarked
         FROM stories s
         JOIN users u ON s.author_id = u.id
         LEFT JOIN story_tags st ON s.id = st.story_id
         LEFT JOIN tags t ON st.tag_id = t.id
-        {}
-        GROUP BY s.id, u.id
-        ORDER BY {}
-        LIMIT $1 OFFSET $2
-        "#,
-        where_clause, order_clause
+        WHERE s.status = 'published' AND s.hidden_at IS NULL"#,
     );
-
-    let rows = sqlx::query_as::<_, StoryFromDb>(&query_str)
-        .bind(limit)
-        .bind(offset)
+    if let Some(viewer_id) = viewer_id {
+        push_viewer_exclusions(&mut qb, viewer_id);
+    }
+    push_story_filters(&mut qb, &filter)?;
+    push_story_keyset(&mut qb, cursor, by_claps);
+    qb.push(" GROUP BY s.id, u.id ORDER BY ");
+    qb.push(story_order_clause(by_claps));
+    // Fetch one extra row to know whether there is a next page
+    qb.push(" LIMIT ");
+    qb.push_bind(limit + 1);
+    qb.push(" OFFSET ");
+    qb.push_bind(offset);
+
+    let mut rows = qb
+        .build_query_as::<StoryFromDb>()
         .fetch_all(&pool)
         .await
         .map_err(|e| {
@@ -316,9 +580,98 @@ pub async fn get_feed(
             AppError::InternalServerError
         })?;
 
+    let pagination = Pagination::from_rows(&mut rows, limit, |s| {
+        if by_claps {
+            Cursor::with_claps(
+                s.clap_count,
+                story_sort_time(s.published_at, s.created_at),
+                s.id,
+            )
+        } else {
+            Cursor::new(story_sort_time(s.published_at, s.created_at), s.id)
+        }
+    });
+
+    let response: Vec<StoryResponse> = rows.into_iter().map(StoryResponse::from).collect();
+
+    Ok(ApiResponse::paginated(response, pagination))
+}
+
+/// List the current user's own stories, drafts included
+/// GET /api/stories/me?status=draft|published|scheduled
+pub async fn get_my_stories(
+    State(pool): State<PgPool>,
+    claims: jwt::Claims,
+    Query(filter): Query<MyStoriesFilter>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = match filter.status.as_deref() {
+        None => None,
+        Some("draft") => Some(StoryStatus::Draft),
+        Some("published") => Some(StoryStatus::Published),
+        Some("scheduled") => Some(StoryStatus::Scheduled),
+        Some(_) => {
+            return Err(AppError::UnprocessableEntity(
+                "status must be one of 'draft', 'published' or 'scheduled'".to_string(),
+            ))
+        }
+    };
+    let limit = filter.limit.unwrap_or(20).clamp(1, 100);
+    let cursor = filter
+        .cursor
+        .as_deref()
+        .map(|c| Cursor::decode(c, false))
+        .transpose()?;
+    // A cursor takes precedence over the legacy offset
+    let offset = if cursor.is_some() {
+        0
+    } else {
+        filter.offset.unwrap_or(0)
+    };
+
+    let mut qb = QueryBuilder::<Postgres>::new(
+        r#"
+        SELECT 
+            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count, s.created_at, s.published_at, s.scheduled_for,
+            u.id as author_id, u.username, u.bio, u.image,
+            story_mentions(s.id) as mentions,
+            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{}') as tags,
+            EXISTS(SELECT 1 FROM bookmarks b WHERE b.story_id = s.id AND b.user_id = u.id) as is_bookmarked
+        FROM stories s
+        JOIN users u ON s.author_id = u.id
+        LEFT JOIN story_tags st ON s.id = st.story_id
+        LEFT JOIN tags t ON st.tag_id = t.id
+        WHERE s.author_id = "#,
+    );
+    qb.push_bind(claims.sub);
+    if let Some(status) = status {
+        qb.push(" AND s.status = ");
+        qb.push_bind(status);
+    }
+    push_story_keyset(&mut qb, cursor, false);
+    qb.push(" GROUP BY s.id, u.id ORDER BY ");
+    qb.push(story_order_clause(false));
+    // Fetch one extra row to know whether there is a next page
+    qb.push(" LIMIT ");
+    qb.push_bind(limit + 1);
+    qb.push(" OFFSET ");
+    qb.push_bind(offset);
+
+    let mut rows = qb
+        .build_query_as::<StoryFromDb>()
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("My stories error: {:?}", e);
+            AppError::InternalServerError
+        })?;
+
+    let pagination = Pagination::from_rows(&mut rows, limit, |s| {
+        Cursor::new(story_sort_time(s.published_at, s.created_at), s.id)
+    });
+
     let response: Vec<StoryResponse> = rows.into_iter().map(StoryResponse::from).collect();
 
-    Ok(ApiResponse::success(response))
+    Ok(ApiResponse::paginated(response, pagination))
 }
 
 pub async fn clap_story(
@@ -332,12 +685,21 @@ pub async fn clap_story(
         .map_err(|_| AppError::InternalServerError)?;
 
     // Check if story exists and is published
-    sqlx::query("SELECT id FROM stories WHERE id = $1 AND status = 'published'")
-        .bind(id)
-        .fetch_optional(&mut *tx)
-        .await
-        .map_err(|_| AppError::InternalServerError)?
-        .ok_or(AppError::NotFound("Story not found".to_string()))?;
+    let story_row =
+        sqlx::query("SELECT author_id FROM stories WHERE id = $1 AND status = 'published' AND hidden_at IS NULL")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|_| AppError::InternalServerError)?
+            .ok_or(AppError::NotFound("Story not found".to_string()))?;
+
+    let story_author_id: Uuid = story_row.get("author_id");
+
+    if blocks::is_blocked(&mut *tx, story_author_id, claims.sub).await? {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot clap for this story".to_string(),
+        ));
+    }
 
     // Check existing claps
     let current_claps_row =
@@ -382,6 +744,16 @@ pub async fn clap_story(
         .await
         .map_err(|_| AppError::InternalServerError)?;
 
+    notifications::notify(
+        &pool,
+        story_author_id,
+        claims.sub,
+        NotificationType::StoryClap,
+        Some(id),
+        None,
+    )
+    .await;
+
     Ok(ApiResponse::ok("Clap recorded".to_string()))
 }
 
@@ -406,6 +778,7 @@ struct StoryFromDb {
     clap_count: i32,
     created_at: chrono::DateTime<chrono::Utc>,
     published_at: Option<chrono::DateTime<chrono::Utc>>,
+    scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
     // author fields
     author_id: Uuid,
     username: String,
@@ -413,6 +786,9 @@ struct StoryFromDb {
     image: Option<String>,
     // tags
     tags: Vec<String>,
+    mentions: sqlx::types::Json<Vec<MentionSpan>>,
+    // whether the viewer bookmarked it
+    is_bookmarked: bool,
 }
 
 impl From<StoryFromDb> for StoryResponse {
@@ -432,22 +808,28 @@ impl From<StoryFromDb> for StoryResponse {
             status: s.status,
             clap_count: s.clap_count,
             tags: s.tags,
+            mentions: s.mentions.0,
+            is_bookmarked: s.is_bookmarked,
             created_at: s.created_at,
             published_at: s.published_at,
+            scheduled_for: s.scheduled_for,
         }
     }
 }
 
-async fn get_story_response(
+pub(crate) async fn get_story_response(
     pool: &PgPool,
     story_id: Uuid,
+    viewer_id: Option<Uuid>,
 ) -> Result<ApiResponse<StoryResponse>, AppError> {
     let row = sqlx::query_as::<_, StoryFromDb>(
         r#"
         SELECT 
-            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count, s.created_at, s.published_at,
+            s.id, s.title, s.subtitle, s.content, s.slug, s.status, s.clap_count, s.created_at, s.published_at, s.scheduled_for,
             u.id as author_id, u.username, u.bio, u.image,
-            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{}') as tags
+            story_mentions(s.id) as mentions,
+            COALESCE(ARRAY_AGG(t.name) FILTER (WHERE t.name IS NOT NULL), '{}') as tags,
+            EXISTS(SELECT 1 FROM bookmarks b WHERE b.story_id = s.id AND b.user_id = $2) as is_bookmarked
         FROM stories s
         JOIN users u ON s.author_id = u.id
         LEFT JOIN story_tags st ON s.id = st.story_id
@@ -457,6 +839,7 @@ async fn get_story_response(
         "#,
     )
     .bind(story_id)
+    .bind(viewer_id)
     .fetch_optional(pool)
     .await
     .map_err(|e| {
@@ -467,3 +850,97 @@ async fn get_story_response(
 
     Ok(ApiResponse::success(StoryResponse::from(row)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &str = "x') OR 1=1; DROP TABLE stories; --";
+
+    fn filter(value: serde_json::Value) -> StoryFilter {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn filter_sql(filter: &StoryFilter) -> Result<String, AppError> {
+        let mut qb = QueryBuilder::<Postgres>::new("WHERE TRUE");
+        push_story_filters(&mut qb, filter)?;
+        Ok(qb.sql().to_string())
+    }
+
+    fn valid_filter_sql(value: serde_json::Value) -> String {
+        filter_sql(&filter(value)).ok().expect("filter is valid")
+    }
+
+    #[test]
+    fn story_filters_bind_user_input() {
+        let sql = valid_filter_sql(serde_json::json!({
+            "tag": PAYLOAD,
+            "tags": format!("rust,{}", PAYLOAD),
+            "author_id": Uuid::nil(),
+            "author": PAYLOAD,
+            "published_after": "2026-01-01T00:00:00Z",
+            "published_before": "2026-02-01T00:00:00Z",
+        }));
+
+        assert_eq!(
+            sql,
+            "WHERE TRUE AND EXISTS (SELECT 1 FROM story_tags fst JOIN tags ft ON fst.tag_id = ft.id WHERE fst.story_id = s.id AND ft.name = ANY($1)) \
+             AND s.author_id = $2 AND u.username = $3 AND s.published_at >= $4 AND s.published_at < $5"
+        );
+        for fragment in ["DROP", "drop", "1=1", "'", "--"] {
+            assert!(!sql.contains(fragment), "{} in {}", fragment, sql);
+        }
+    }
+
+    #[test]
+    fn story_filters_bind_all_tags_mode() {
+        let sql = valid_filter_sql(serde_json::json!({
+            "tags": PAYLOAD,
+            "tag_mode": "all",
+        }));
+
+        assert!(sql.ends_with("ft.name = ANY($1)) = $2"), "{}", sql);
+        assert!(!sql.to_lowercase().contains("drop"));
+    }
+
+    #[test]
+    fn story_filters_reject_bad_input() {
+        let tags: Vec<String> = (0..11).map(|i| format!("tag{}", i)).collect();
+        assert!(matches!(
+            filter_sql(&filter(serde_json::json!({ "tags": tags.join(",") }))),
+            Err(AppError::UnprocessableEntity(_))
+        ));
+        assert!(matches!(
+            filter_sql(&filter(
+                serde_json::json!({ "tag": "rust", "tag_mode": PAYLOAD })
+            )),
+            Err(AppError::UnprocessableEntity(_))
+        ));
+    }
+
+    #[test]
+    fn story_keyset_follows_the_sort_order() {
+        let cursor = Cursor::decode(
+            &Cursor::new(chrono::Utc::now(), Uuid::nil()).encode(),
+            false,
+        )
+        .ok()
+        .expect("cursor round-trips");
+        let mut qb = QueryBuilder::<Postgres>::new("WHERE TRUE");
+        push_story_keyset(&mut qb, Some(cursor), false);
+
+        assert_eq!(
+            qb.sql(),
+            "WHERE TRUE AND (COALESCE(s.published_at, s.created_at), s.id) < ($1, $2)"
+        );
+        assert!(
+            story_order_clause(false).starts_with("COALESCE(s.published_at, s.created_at) DESC")
+        );
+        assert!(story_order_clause(true).contains("COALESCE(s.published_at, s.created_at) DESC"));
+    }
+
+    #[test]
+    fn story_filters_add_nothing_when_empty() {
+        assert_eq!(valid_filter_sql(serde_json::json!({})), "WHERE TRUE");
+    }
+}
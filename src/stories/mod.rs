@@ -3,7 +3,10 @@ use sqlx::prelude::Type;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::mentions::MentionSpan;
+
 pub mod handler;
+pub mod publisher;
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Story {
@@ -18,6 +21,7 @@ pub struct Story {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Type, PartialEq, Eq)]
@@ -25,6 +29,7 @@ pub struct Story {
 pub enum StoryStatus {
     Draft,
     Published,
+    Scheduled,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -41,6 +46,7 @@ pub struct CreateStory {
     pub content: serde_json::Value,
     pub tags: Vec<String>,
     pub publish: bool,
+    pub scheduled_for: Option<chrono::DateTime<chrono::Utc>>, // Publish automatically at this time
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -51,6 +57,7 @@ pub struct UpdateStory {
     pub content: Option<serde_json::Value>,
     pub tags: Option<Vec<String>>,
     pub publish: Option<bool>,
+    pub scheduled_for: Option<chrono::DateTime<chrono::Utc>>, // Schedule the story to publish at this time
 }
 
 #[derive(Debug, Serialize)]
@@ -64,8 +71,11 @@ pub struct StoryResponse {
     pub status: StoryStatus,
     pub clap_count: i32,
     pub tags: Vec<String>,
+    pub mentions: Vec<MentionSpan>, // @username mentions in the content
+    pub is_bookmarked: bool,        // Whether the current user bookmarked this story
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub published_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub scheduled_for: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -79,7 +89,40 @@ pub struct AuthorResponse {
 #[derive(Debug, Deserialize)]
 pub struct StoryFilter {
     pub tag: Option<String>,
+    pub tags: Option<String>,     // Comma-separated tag names
+    pub tag_mode: Option<String>, // "any" (default) or "all"
+    pub author_id: Option<Uuid>,
+    pub author: Option<String>, // Author username
+    pub published_after: Option<chrono::DateTime<chrono::Utc>>,
+    pub published_before: Option<chrono::DateTime<chrono::Utc>>,
     pub limit: Option<i64>,
-    pub offset: Option<i64>,
+    pub offset: Option<i64>, // Legacy, prefer cursor
+    pub cursor: Option<String>,
     pub sort: Option<String>, // "latest" or "claps"
 }
+
+/// Query params for the author's own story list
+#[derive(Debug, Deserialize)]
+pub struct MyStoriesFilter {
+    pub status: Option<String>, // "draft", "published" or "scheduled", omit for all
+    pub limit: Option<i64>,
+    pub offset: Option<i64>, // Legacy, prefer cursor
+    pub cursor: Option<String>,
+}
+
+impl StoryFilter {
+    /// Tag names from `tag` and `tags`, normalized the way tags are stored
+    pub fn tag_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .tag
+            .iter()
+            .chain(self.tags.iter())
+            .flat_map(|t| t.split(','))
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
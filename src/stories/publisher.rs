@@ -0,0 +1,67 @@
+use sqlx::{PgPool, Row};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    email::EmailService,
+    mentions::{self, MentionTarget},
+};
+
+/// Maximum number of stories promoted per tick; the rest wait for the next one
+const BATCH_SIZE: i64 = 100;
+
+/// Spawn the background task that publishes scheduled stories once they are due.
+///
+/// Several API instances can run this at once: due rows are claimed with
+/// `FOR UPDATE SKIP LOCKED`, so each story is published by exactly one of them.
+pub fn spawn(pool: PgPool, email_service: EmailService, interval_secs: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs.max(1)));
+
+        loop {
+            interval.tick().await;
+
+            match publish_due_stories(&pool).await {
+                Ok(published) if !published.is_empty() => {
+                    tracing::info!("Published {} scheduled stories", published.len());
+
+                    // People mentioned in a scheduled story hear about it once it's out
+                    for story_id in published {
+                        mentions::notify(&pool, &email_service, MentionTarget::Story(story_id))
+                            .await;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Scheduled publisher error: {:?}", e),
+            }
+        }
+    });
+}
+
+/// Promote due scheduled stories to published, returning their ids
+pub async fn publish_due_stories(pool: &PgPool) -> Result<Vec<Uuid>, sqlx::Error> {
+    // Same effect as publishing through update_story: status, published_at
+    // and updated_at change, and the schedule is cleared
+    let rows = sqlx::query(
+        r#"
+        UPDATE stories
+        SET status = 'published',
+            published_at = COALESCE(published_at, NOW()),
+            scheduled_for = NULL,
+            updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM stories
+            WHERE status = 'scheduled' AND scheduled_for <= NOW()
+            ORDER BY scheduled_for
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id
+        "#,
+    )
+    .bind(BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|row| row.get("id")).collect())
+}
@@ -0,0 +1,152 @@
+#!/bin/bash
+# Social sign-in against a local mock OpenID Connect provider: account creation,
+# linking by verified email, untrusted providers, PKCE/state handling and
+# ID token signature checks.
+#
+# The server must be started with two providers pointing at the mock, one trusted:
+#   OAUTH_PROVIDERS=mock,untrusted
+#   OAUTH_MOCK_ISSUER=http://localhost:9000 OAUTH_MOCK_CLIENT_ID=blogverse
+#   OAUTH_MOCK_CLIENT_SECRET=secret OAUTH_MOCK_TRUSTED_EMAIL=true
+#   OAUTH_UNTRUSTED_ISSUER=http://localhost:9000 OAUTH_UNTRUSTED_CLIENT_ID=blogverse
+#   OAUTH_UNTRUSTED_CLIENT_SECRET=secret
+#
+# Usage: ./test_oauth.sh  (server running on BASE_URL, default http://localhost:8000;
+# the mock provider is started on port 9000 unless MOCK_RUNNING=1)
+BASE_URL=${BASE_URL:-http://localhost:8000}
+EMAIL=${EMAIL:-test@example.com}
+PASSWORD=${PASSWORD:-password123}
+RUN=$(date +%s)
+FAILED=0
+
+if [ "$MOCK_RUNNING" != "1" ]; then
+    python3 "$(dirname "$0")/scripts/mock_oidc_provider.py" --port 9000 &
+    MOCK_PID=$!
+    trap 'kill $MOCK_PID' EXIT
+    sleep 1
+fi
+
+check() {
+    if [ "$2" = "$3" ]; then
+        echo "PASS: $1"
+    else
+        echo "FAIL: $1 (expected $3, got $2)"
+        FAILED=1
+    fi
+}
+
+json_field() {
+    echo "$1" | grep -o "\"$2\":\"[^\"]*\"" | head -1 | sed "s/\"$2\":\"//;s/\"$//"
+}
+
+# Go through the provider with the given claims; prints the code and state from the
+# redirect back to the frontend
+provider_redirect() {
+    curl -s -o /dev/null -w '%{redirect_url}' "$1&$2" | sed 's/.*?//'
+}
+
+# Full sign-in through a provider; prints the callback response
+oauth_sign_in() {
+    local provider=$1 claims=$2
+    local authorize=$(curl -s "$BASE_URL/api/auth/oauth/$provider/authorize")
+    local url=$(json_field "$authorize" authorization_url | sed 's/\\u0026/\&/g')
+    local redirect=$(provider_redirect "$url" "$claims")
+    local code=$(echo "$redirect" | grep -o 'code=[^&]*' | cut -d= -f2)
+    local state=$(echo "$redirect" | grep -o 'state=[^&]*' | cut -d= -f2)
+    curl -s -X POST "$BASE_URL/api/auth/oauth/$provider/callback" -H "Content-Type: application/json" \
+        -d "{\"code\": \"$code\", \"state\": \"$state\"}"
+}
+
+succeeded() {
+    echo "$1" | grep -c '"success":true'
+}
+
+echo "=== Providers ==="
+RESULT=$(curl -s "$BASE_URL/api/auth/oauth/providers")
+echo "$RESULT"
+check "mock provider is listed" "$(echo "$RESULT" | grep -c '"mock"')" "1"
+echo ""
+
+echo "=== New account through a trusted provider ==="
+RESULT=$(oauth_sign_in mock "sub=new-$RUN&email=oauth-$RUN@example.com&preferred_username=oauth$RUN")
+echo "$RESULT"
+TOKEN=$(json_field "$RESULT" token)
+NEW_USER_ID=$(echo "$RESULT" | grep -o '"user":{"id":"[^"]*"' | cut -d'"' -f6)
+check "sign-in returns a token" "$([ -n "$TOKEN" ] && echo yes)" "yes"
+check "email is verified" "$(echo "$RESULT" | grep -c '"email_verified":true')" "1"
+echo ""
+
+echo "=== Same identity signs in to the same account ==="
+RESULT=$(oauth_sign_in mock "sub=new-$RUN&email=oauth-$RUN@example.com")
+check "same user" "$(echo "$RESULT" | grep -o '"user":{"id":"[^"]*"' | cut -d'"' -f6)" "$NEW_USER_ID"
+echo ""
+
+echo "=== Existing account is linked by verified email ==="
+LOGIN_RESPONSE=$(curl -s -X POST "$BASE_URL/api/auth/sign-in" -H "Content-Type: application/json" -d "{\"email\": \"$EMAIL\", \"password\": \"$PASSWORD\"}")
+PASSWORD_TOKEN=$(json_field "$LOGIN_RESPONSE" token)
+EXISTING_ID=$(echo "$LOGIN_RESPONSE" | grep -o '"user":{"id":"[^"]*"' | cut -d'"' -f6)
+RESULT=$(oauth_sign_in mock "sub=existing-$RUN&email=$EMAIL")
+check "linked to the password account" "$(echo "$RESULT" | grep -o '"user":{"id":"[^"]*"' | cut -d'"' -f6)" "$EXISTING_ID"
+RESULT=$(curl -s "$BASE_URL/api/auth/me/identities" -H "Authorization: Bearer $PASSWORD_TOKEN")
+echo "$RESULT"
+check "identity is listed" "$(echo "$RESULT" | grep -c '"provider":"mock"')" "1"
+RESULT=$(curl -s -X DELETE "$BASE_URL/api/auth/me/identities/mock" -H "Authorization: Bearer $PASSWORD_TOKEN")
+check "identity unlinked" "$(succeeded "$RESULT")" "1"
+echo ""
+
+echo "=== Untrusted provider does not link by email ==="
+RESULT=$(oauth_sign_in untrusted "sub=claim-$RUN&email=$EMAIL")
+echo "$RESULT"
+check "conflict instead of takeover" "$(echo "$RESULT" | grep -c 'already exists')" "1"
+echo ""
+
+echo "=== Untrusted provider creates an unverified account ==="
+RESULT=$(oauth_sign_in untrusted "sub=unverified-$RUN&email=untrusted-$RUN@example.com")
+echo "$RESULT"
+check "email must be verified first" "$(echo "$RESULT" | grep -c 'verify your email')" "1"
+echo ""
+
+echo "=== Unverified email at a trusted provider does not link ==="
+RESULT=$(oauth_sign_in mock "sub=unverified-claim-$RUN&email=$EMAIL&email_verified=false")
+check "conflict" "$(echo "$RESULT" | grep -c 'already exists')" "1"
+echo ""
+
+echo "=== State can only be used once ==="
+AUTHORIZE=$(curl -s "$BASE_URL/api/auth/oauth/mock/authorize")
+URL=$(json_field "$AUTHORIZE" authorization_url | sed 's/\\u0026/\&/g')
+REDIRECT=$(provider_redirect "$URL" "sub=new-$RUN")
+CODE=$(echo "$REDIRECT" | grep -o 'code=[^&]*' | cut -d= -f2)
+STATE=$(echo "$REDIRECT" | grep -o 'state=[^&]*' | cut -d= -f2)
+check "PKCE challenge sent" "$(echo "$URL" | grep -c 'code_challenge_method=S256')" "1"
+curl -s -o /dev/null -X POST "$BASE_URL/api/auth/oauth/mock/callback" -H "Content-Type: application/json" -d "{\"code\": \"$CODE\", \"state\": \"$STATE\"}"
+RESULT=$(curl -s -o /dev/null -w '%{http_code}' -X POST "$BASE_URL/api/auth/oauth/mock/callback" -H "Content-Type: application/json" -d "{\"code\": \"$CODE\", \"state\": \"$STATE\"}")
+check "replayed state rejected" "$RESULT" "400"
+echo ""
+
+echo "=== ID token signed with an unknown key ==="
+RESULT=$(oauth_sign_in mock "sub=new-$RUN&tamper=1")
+check "rejected" "$(echo "$RESULT" | grep -c 'Unauthorized')" "1"
+echo ""
+
+echo "=== Linking a provider from account settings ==="
+AUTHORIZE=$(curl -s -X POST "$BASE_URL/api/auth/me/identities/untrusted" -H "Authorization: Bearer $TOKEN")
+URL=$(json_field "$AUTHORIZE" authorization_url | sed 's/\\u0026/\&/g')
+REDIRECT=$(provider_redirect "$URL" "sub=linked-$RUN&email=other-$RUN@example.com")
+CODE=$(echo "$REDIRECT" | grep -o 'code=[^&]*' | cut -d= -f2)
+STATE=$(echo "$REDIRECT" | grep -o 'state=[^&]*' | cut -d= -f2)
+RESULT=$(curl -s -o /dev/null -w '%{http_code}' -X POST "$BASE_URL/api/auth/oauth/untrusted/callback" -H "Content-Type: application/json" -d "{\"code\": \"$CODE\", \"state\": \"$STATE\"}")
+check "linking state rejected by the sign-in callback" "$RESULT" "400"
+RESULT=$(curl -s -o /dev/null -w '%{http_code}' -X POST "$BASE_URL/api/auth/me/identities/untrusted/callback" -H "Content-Type: application/json" -H "Authorization: Bearer $PASSWORD_TOKEN" -d "{\"code\": \"$CODE\", \"state\": \"$STATE\"}")
+check "linking state rejected for another user" "$RESULT" "400"
+RESULT=$(curl -s -X POST "$BASE_URL/api/auth/me/identities/untrusted/callback" -H "Content-Type: application/json" -H "Authorization: Bearer $TOKEN" -d "{\"code\": \"$CODE\", \"state\": \"$STATE\"}")
+echo "$RESULT"
+check "linked" "$(echo "$RESULT" | grep -c '"provider":"untrusted"')" "1"
+RESULT=$(oauth_sign_in untrusted "sub=linked-$RUN&email=other-$RUN@example.com")
+check "linked identity signs in" "$(echo "$RESULT" | grep -o '"user":{"id":"[^"]*"' | cut -d'"' -f6)" "$NEW_USER_ID"
+echo ""
+
+if [ $FAILED -eq 0 ]; then
+    echo "All OAuth checks passed"
+else
+    echo "Some OAuth checks failed"
+    exit 1
+fi